@@ -1,5 +1,7 @@
 use clap::{App, Arg};
 use easy_fs::{BlockDevice, EasyFileSystem};
+#[cfg(test)]
+use easy_fs::Inode;
 use std::fs::{read_dir, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
@@ -80,8 +82,8 @@ fn easy_fs_pack() -> std::io::Result<()> {
         // write data to easy-fs
         inode.write_at(0, all_data.as_slice());
     }
-    // list apps
-    for app in root_inode.ls() {
+    // list apps, skipping "." and ".."
+    for app in root_inode.ls().iter().filter(|name| *name != "." && *name != "..") {
         println!("{}", app);
     }
     Ok(())
@@ -115,6 +117,8 @@ fn efs_test() -> std::io::Result<()> {
     let mut buffer = [0u8; 233];
     let len = filea.read_at(0, &mut buffer);
     assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap(),);
+    assert_eq!(filea.size() as usize, greet_str.len());
+    assert_eq!(filea.blocks(), 1);
 
     let mut random_str_test = |len: usize| {
         filea.clear();
@@ -126,6 +130,8 @@ fn efs_test() -> std::io::Result<()> {
             str.push(char::from('0' as u8 + rand::random::<u8>() % 10));
         }
         filea.write_at(0, str.as_bytes());
+        assert_eq!(filea.size() as usize, len);
+        assert!(filea.blocks() as usize >= (len + BLOCK_SZ - 1) / BLOCK_SZ);
         let mut read_buffer = [0u8; 127];
         let mut offset = 0usize;
         let mut read_str = String::new();
@@ -149,5 +155,180 @@ fn efs_test() -> std::io::Result<()> {
     random_str_test(1000 * BLOCK_SZ);
     random_str_test(2000 * BLOCK_SZ);
 
+    //块缓存是全局的且只以块编号区分，因此多级目录的测试在同一个文件系统上接着进行
+    efs_dir_test(&root_inode);
+    efs_unlink_test(&root_inode);
+    efs_link_test(&root_inode);
+    efs_symlink_test(&root_inode);
     Ok(())
 }
+
+#[cfg(test)]
+fn efs_dir_test(root_inode: &Inode) {
+    //根目录的 . 和 .. 都指向它自身
+    assert_eq!(root_inode.find(".").unwrap().inode_id(), 0);
+    assert_eq!(root_inode.find("..").unwrap().inode_id(), 0);
+
+    let dir = root_inode.create_dir("dir").unwrap();
+    assert!(dir.is_dir() && !dir.is_file());
+    assert!(root_inode.create_dir("dir").is_none());
+    assert!(root_inode.create("dir").is_none());
+    assert_eq!(dir.ls(), vec![".", ".."]);
+    assert_eq!(dir.find(".").unwrap().inode_id(), dir.inode_id());
+    assert_eq!(dir.find("..").unwrap().inode_id(), root_inode.inode_id());
+
+    let sub = dir.create_dir("sub").unwrap();
+    let file = sub.create("file").unwrap();
+    assert!(file.is_file());
+    file.write_at(0, b"nested");
+    let file = root_inode
+        .find("dir")
+        .and_then(|dir| dir.find("sub"))
+        .and_then(|sub| sub.find("file"))
+        .unwrap();
+    let mut buffer = [0u8; 16];
+    let len = file.read_at(0, &mut buffer);
+    assert_eq!(&buffer[..len], b"nested");
+    assert_eq!(
+        sub.find("..").and_then(|dir| dir.find("..")).unwrap().inode_id(),
+        0
+    );
+    //文件名过长或包含 / 时创建失败
+    assert!(root_inode.create("a_name_that_is_much_too_long").is_none());
+    assert!(root_inode.create("a/b").is_none());
+}
+
+
+#[cfg(test)]
+fn efs_unlink_test(root_inode: &Inode) {
+    //非空目录、. 和 .. 以及不存在的文件都不能删除
+    assert!(!root_inode.unlink("dir"));
+    assert!(!root_inode.unlink("."));
+    assert!(!root_inode.unlink(".."));
+    assert!(!root_inode.unlink("nonexistent"));
+
+    let dir = root_inode.find("dir").unwrap();
+    let sub = dir.find("sub").unwrap();
+    assert!(sub.unlink("file"));
+    assert!(sub.find("file").is_none());
+    assert!(dir.unlink("sub"));
+    assert!(root_inode.unlink("dir"));
+    assert!(root_inode.find("dir").is_none());
+
+    //被删除的目录项留下的空位会被复用
+    let names = root_inode.ls();
+    assert!(root_inode.unlink("filea"));
+    root_inode.create("filec").unwrap();
+    let expected: Vec<String> = names
+        .iter()
+        .map(|name| if name == "filea" { "filec".into() } else { name.clone() })
+        .collect();
+    assert_eq!(root_inode.ls(), expected);
+
+    //反复创建并删除文件，inode 和数据块如果没有回收很快就会耗尽
+    let data = [0x5au8; 20 * BLOCK_SZ];
+    for _ in 0..5000 {
+        let file = root_inode.create("tmp").unwrap();
+        assert_eq!(file.write_at(0, &data), data.len());
+        assert!(root_inode.unlink("tmp"));
+    }
+
+    //unlink_entry 只删除目录项，inode 在 reclaim 之前仍然可以读写，也不会被新文件复用
+    let file = root_inode.create("opened").unwrap();
+    file.write_at(0, b"still here");
+    let unlinked = root_inode.unlink_entry("opened").unwrap();
+    assert_eq!(unlinked.inode_id(), file.inode_id());
+    assert!(root_inode.find("opened").is_none());
+    file.write_at(10, b"!");
+    let mut buffer = [0u8; 16];
+    let len = file.read_at(0, &mut buffer);
+    assert_eq!(&buffer[..len], b"still here!");
+    let other = root_inode.create("other").unwrap();
+    assert_ne!(other.inode_id(), file.inode_id());
+    file.reclaim();
+    let reused = root_inode.create("reused").unwrap();
+    assert_eq!(reused.inode_id(), file.inode_id());
+    assert_eq!(reused.read_at(0, &mut buffer), 0);
+    assert!(root_inode.unlink("reused"));
+    assert!(root_inode.unlink("other"));
+
+    //被删除的目录中不再有 . 和 .. ，也不能在其中新建文件
+    let dir = root_inode.create_dir("removed").unwrap();
+    root_inode.unlink_entry("removed").unwrap();
+    assert!(dir.ls().is_empty());
+    assert!(dir.find("..").is_none());
+    assert!(dir.create("file").is_none());
+    assert!(dir.create_dir("sub").is_none());
+    dir.reclaim();
+}
+
+#[cfg(test)]
+fn efs_link_test(root_inode: &Inode) {
+    //目录的链接数是 2 加上子目录的个数
+    let root_nlink = root_inode.nlink();
+    let dir = root_inode.create_dir("linkdir").unwrap();
+    assert_eq!(dir.nlink(), 2);
+    assert_eq!(root_inode.nlink(), root_nlink + 1);
+    dir.create_dir("sub").unwrap();
+    assert_eq!(dir.nlink(), 3);
+    assert!(dir.unlink("sub"));
+    assert_eq!(dir.nlink(), 2);
+
+    //不同目录中的多个名字指向同一个 inode
+    let file = root_inode.create("origin").unwrap();
+    file.write_at(0, b"shared");
+    assert_eq!(file.nlink(), 1);
+    assert!(dir.link("alias", &file));
+    assert!(root_inode.link("alias", &file));
+    assert_eq!(file.nlink(), 3);
+    assert_eq!(dir.find("alias").unwrap().inode_id(), file.inode_id());
+    //已经存在的名字、目录以及 . 和 .. 都不能作为链接
+    assert!(!dir.link("alias", &file));
+    assert!(!root_inode.link("dirlink", &dir));
+    assert!(!dir.link("..", &file));
+
+    //删除其中一个名字后，通过其他名字仍然能读到内容，inode 也不会被复用
+    assert!(root_inode.unlink("origin"));
+    assert_eq!(file.nlink(), 2);
+    let other = root_inode.create("other").unwrap();
+    assert_ne!(other.inode_id(), file.inode_id());
+    let mut buffer = [0u8; 16];
+    let alias = root_inode.find("alias").unwrap();
+    let len = alias.read_at(0, &mut buffer);
+    assert_eq!(&buffer[..len], b"shared");
+    assert!(root_inode.unlink("alias"));
+    assert!(dir.unlink("alias"));
+    assert_eq!(file.nlink(), 0);
+    //最后一个名字被删除时 inode 被回收，之后可以被新文件复用
+    let reused = root_inode.create("reused").unwrap();
+    assert_eq!(reused.inode_id(), file.inode_id());
+    assert_eq!(reused.nlink(), 1);
+    assert!(root_inode.unlink("reused"));
+    assert!(root_inode.unlink("other"));
+    assert!(root_inode.unlink("linkdir"));
+    assert_eq!(root_inode.nlink(), root_nlink);
+}
+
+#[cfg(test)]
+fn efs_symlink_test(root_inode: &Inode) {
+    //符号链接保存的是路径本身，不要求它指向的文件存在
+    let link = root_inode.create_symlink("link", "dir/../target").unwrap();
+    assert!(link.is_symlink() && !link.is_file() && !link.is_dir());
+    assert_eq!(link.nlink(), 1);
+    assert_eq!(link.read_link().unwrap(), "dir/../target");
+    assert_eq!(root_inode.find("link").unwrap().inode_id(), link.inode_id());
+    //普通文件和目录不是符号链接
+    assert!(root_inode.read_link().is_none());
+    let file = root_inode.create("target").unwrap();
+    assert!(file.read_link().is_none());
+    //空路径不能作为符号链接的内容，已经存在的名字也不能再创建符号链接
+    assert!(root_inode.create_symlink("empty", "").is_none());
+    assert!(root_inode.create_symlink("target", "link").is_none());
+    //符号链接也可以建立硬链接，删除时只删除链接本身
+    assert!(root_inode.link("link2", &link));
+    assert!(root_inode.unlink("link"));
+    assert_eq!(root_inode.find("link2").unwrap().read_link().unwrap(), "dir/../target");
+    assert!(root_inode.unlink("link2"));
+    assert!(root_inode.find("target").is_some());
+    assert!(root_inode.unlink("target"));
+}
@@ -82,8 +82,12 @@ impl EasyFileSystem {
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
                 disk_inode.initialize(DiskInodeType::Directory);
             });
+        let efs = Arc::new(Mutex::new(efs));
+        //根目录的 . 和 .. 都指向它自身
+        let root_inode = Self::root_inode(&efs);
+        root_inode.initialize_dir(0, &mut efs.lock());
         block_cache_sync_all();
-        Arc::new(Mutex::new(efs))
+        efs
     }
 
     //从一个已写入了 easy-fs 镜像的块设备上打开easy-fs 
@@ -117,7 +121,7 @@ impl EasyFileSystem {
         // acquire efs lock temporarily
         let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
         // release efs lock
-        Inode::new(block_id, block_offset, Arc::clone(efs), block_device)
+        Inode::new(0, block_id, block_offset, Arc::clone(efs), block_device)
     }
 
     //从 inode位图 或数据块位图上分配的 bit 编号，来算出各个存储inode和数据块的磁盘块在磁盘上的实际位置
@@ -139,9 +143,18 @@ impl EasyFileSystem {
         self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
     }
 
+    //回收一个 inode ，调用者需保证它占据的数据块已经全部回收
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
+    }
+
     ///配/回收数据块传入/返回的参数都表示数据块在块设备上的编号，而不是在数据块位图中分配的bit编号
-    pub fn alloc_data(&mut self) -> u32 {
-        self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
+    ///数据块已经用完时返回 None
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        self.data_bitmap
+            .alloc(&self.block_device)
+            .map(|bit| bit as u32 + self.data_area_start_block)
     }
 
     pub fn dealloc_data(&mut self, block_id: u32) {
@@ -3,15 +3,18 @@ use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::fmt::{Debug, Formatter, Result};
 
-const EFS_MAGIC: u32 = 0x3b800001;
-const INODE_DIRECT_COUNT: usize = 28;
-const NAME_LENGTH_LIMIT: usize = 27;//目录项 Dirent 最大允许保存长度为 27 的文件/目录名
+//DiskInode 中加入 nlink 之后磁盘布局发生了变化，修改魔数使旧格式的镜像不会被误读
+const EFS_MAGIC: u32 = 0x3b800002;
+//留出 nlink 的位置，使 DiskInode 仍然恰好占 128 字节
+const INODE_DIRECT_COUNT: usize = 27;
+pub const NAME_LENGTH_LIMIT: usize = 27;//目录项 Dirent 最大允许保存长度为 27 的文件/目录名
 const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
 const INODE_INDIRECT2_COUNT: usize = INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COUNT;
 const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
-#[allow(unused)]
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
+//一个文件最多能用到的字节数，直接索引、一级间接索引和二级间接索引的数据块全部用上
+pub const MAX_FILE_SIZE: usize = INDIRECT2_BOUND * BLOCK_SZ;
 
 //磁盘上的数据结构，存放在磁盘上编号为 0 的块的起始处
 #[repr(C)]
@@ -65,6 +68,8 @@ impl SuperBlock {
 pub enum DiskInodeType {
     File,
     Directory,
+    //符号链接，内容是它指向的路径
+    Symlink,
 }
 
 type IndirectBlock = [u32; BLOCK_SZ / 4];
@@ -79,7 +84,9 @@ pub struct DiskInode {
     pub direct: [u32; INODE_DIRECT_COUNT],//直接索引
     pub indirect1: u32,//一级间接索引
     pub indirect2: u32,//二级间接索引
-    type_: DiskInodeType,//表示索引节点的类型, 目前仅支持文件File和目录Directory两种类型
+    //指向该 inode 的目录项个数。目录的 . 和子目录中的 .. 也计算在内，降为 0 时 inode 才能被回收
+    pub nlink: u32,
+    type_: DiskInodeType,//表示索引节点的类型, 支持文件File、目录Directory和符号链接Symlink三种类型
 }
 
 impl DiskInode {
@@ -90,6 +97,8 @@ impl DiskInode {
         //indirect1/2 均被初始化为 0 。因为最开始文件内容的大小为 0 字节，并不会用到一级/二级索引
         self.indirect1 = 0;
         self.indirect2 = 0;
+        //新文件只有父目录中的一个目录项，新目录还要加上它自己的 .
+        self.nlink = if type_ == DiskInodeType::Directory { 2 } else { 1 };
         self.type_ = type_;
     }
     //确认 DiskInode 的类型为文件还是目录
@@ -100,6 +109,9 @@ impl DiskInode {
     pub fn is_file(&self) -> bool {
         self.type_ == DiskInodeType::File
     }
+    pub fn is_symlink(&self) -> bool {
+        self.type_ == DiskInodeType::Symlink
+    }
     /// 计算为了容纳自身 size 字节的内容需要多少个数据块
     pub fn data_blocks(&self) -> u32 {
         Self::_data_blocks(self.size)
@@ -1,13 +1,15 @@
 use super::{
     block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    EasyFileSystem, DIRENT_SZ, MAX_FILE_SIZE, NAME_LENGTH_LIMIT,
 };
 use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
 
 pub struct Inode {
+    inode_id: u32,
     block_id: usize,
     block_offset: usize,
     fs: Arc<Mutex<EasyFileSystem>>,
@@ -24,6 +26,7 @@ pub struct Inode {
 impl Inode {
     /// We should not acquire efs lock here.
     pub fn new(
+        inode_id: u32,
         block_id: u32,
         block_offset: usize,
         //指向EasyFileSystem的一个指针，因为对Inode的种种操作实际上都是要通过底层的文件系统来完成
@@ -31,6 +34,7 @@ impl Inode {
         block_device: Arc<dyn BlockDevice>,
     ) -> Self {
         Self {
+            inode_id,
             block_id: block_id as usize,
             block_offset,
             fs,
@@ -53,6 +57,10 @@ impl Inode {
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
         // assert it is a directory
         assert!(disk_inode.is_dir());
+        //文件名为空的目录项是已被删除的空位
+        if name.is_empty() {
+            return None;
+        }
         let file_count = (disk_inode.size as usize) / DIRENT_SZ;
         let mut dirent = DirEntry::empty();
         for i in 0..file_count {
@@ -67,52 +75,151 @@ impl Inode {
         None
     }
 
-    //文件索引的查找仅需在根目录的目录项中根据文件名找到文件的 inode 编号即可。
+    //根据 inode 编号得到同一文件系统中的另一个 Inode
+    fn get_inode(&self, inode_id: u32, fs: &MutexGuard<EasyFileSystem>) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        Arc::new(Self::new(
+            inode_id,
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+
+    //在当前目录的目录项中根据文件名找到文件的 inode 编号。
+    //多级路径的逐级查找由调用者完成
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
         let fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-                Arc::new(Self::new(
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
-            })
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| self.get_inode(inode_id, &fs))
         })
     }
 
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+
+    pub fn is_file(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_file())
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_symlink())
+    }
+
+    //指向该 inode 的目录项个数
+    pub fn nlink(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.nlink)
+    }
+
+    //文件内容的字节数
+    pub fn size(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size)
+    }
+
+    //文件占据的数据块和索引块的总数
+    pub fn blocks(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| DiskInode::total_blocks(disk_inode.size))
+    }
+
     fn increase_size(
         &self,
         new_size: u32,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
-    ) {
+    ) -> bool {
         if new_size < disk_inode.size {
-            return;
+            return true;
         }
         let blocks_needed = disk_inode.blocks_num_needed(new_size);
         let mut v: Vec<u32> = Vec::new();
         for _ in 0..blocks_needed {
-            v.push(fs.alloc_data());
+            match fs.alloc_data() {
+                Some(block_id) => v.push(block_id),
+                //磁盘空间不够时归还已经分配的数据块，文件大小保持不变
+                None => {
+                    for block_id in v {
+                        fs.dealloc_data(block_id);
+                    }
+                    return false;
+                }
+            }
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
+        true
     }
 
-    //在根目录下创建一个文件
-    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
-        let mut fs = self.fs.lock();
-        let op = |root_inode: &DiskInode| {
+    //向目录中插入一个目录项，优先复用被删除的目录项留下的空位，没有空位时追加到末尾
+    fn append_dirent(
+        &self,
+        name: &str,
+        inode_id: u32,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        let slot = (0..file_count)
+            .find(|&i| {
+                dir_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                dirent.name().is_empty()
+            })
+            .unwrap_or(file_count);
+        if slot == file_count {
+            // increase size
+            assert!(
+                self.increase_size(((file_count + 1) * DIRENT_SZ) as u32, dir_inode, fs),
+                "no free data block for a directory entry"
+            );
+        }
+        // write dirent
+        let dirent = DirEntry::new(name, inode_id);
+        dir_inode.write_at(slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+    }
+
+    //为一个新建的目录写入 . 和 .. 两个目录项，分别指向它自身和父目录
+    pub(crate) fn initialize_dir(&self, parent_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        self.modify_disk_inode(|dir_inode| {
+            assert!(dir_inode.is_dir());
+            self.append_dirent(".", self.inode_id, dir_inode, fs);
+            self.append_dirent("..", parent_id, dir_inode, fs);
+        });
+    }
+
+    //检查能否在当前目录中新增一个名为 name 的目录项
+    fn can_add_entry(&self, name: &str) -> bool {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT || name.contains('/') {
+            return false;
+        }
+        self.read_disk_inode(|dir_inode| {
             // assert it is a directory
-            assert!(root_inode.is_dir());
-            // has the file been created?
-            self.find_inode_id(name, root_inode)
-        };
-        //检查文件是否已经在根目录下，如果找到的话返回 None
-        if self.read_disk_inode(op).is_some() {
+            assert!(dir_inode.is_dir());
+            //检查文件是否已经在当前目录下。已经被删除的目录中没有 . 和 .. ，
+            //也不能再新增目录项，否则新文件再也无法被删除和回收
+            self.find_inode_id(name, dir_inode).is_none()
+                && self.find_inode_id(".", dir_inode).is_some()
+        })
+    }
+
+    //在当前目录下创建一个类型为 type_ 的索引节点
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        if !self.can_add_entry(name) {
             return None;
         }
+        let is_dir = type_ == DiskInodeType::Directory;
         //为待创建文件分配一个新的 inode 并进行初始化
         let new_inode_id = fs.alloc_inode();
         // initialize inode
@@ -120,37 +227,74 @@ impl Inode {
         get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
+                new_inode.initialize(type_);
             });
-            //将待创建文件的目录项插入到根目录的内容中，使得之后可以索引到
-        self.modify_disk_inode(|root_inode| {
-            // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
-            let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
+        //将待创建文件的目录项插入到当前目录的内容中，使得之后可以索引到
+        self.modify_disk_inode(|dir_inode| {
+            self.append_dirent(name, new_inode_id, dir_inode, &mut fs);
         });
-
-        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        let new_inode = self.get_inode(new_inode_id, &fs);
+        if is_dir {
+            new_inode.initialize_dir(self.inode_id, &mut fs);
+            //子目录中的 .. 指向当前目录
+            self.modify_disk_inode(|dir_inode| dir_inode.nlink += 1);
+        }
         block_cache_sync_all();
         // return inode
-        Some(Arc::new(Self::new(
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Some(new_inode)
         // release efs lock automatically by compiler
     }
 
-    //收集根目录下的所有文件的文件名并以向量的形式返回
+    //在当前目录下创建一个文件
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+
+    //在当前目录下创建一个子目录
+    pub fn create_dir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+
+    //在当前目录下创建一个指向路径 target 的符号链接，target 原样保存在符号链接的内容中，
+    //不检查它是否存在，相对路径在使用时从符号链接所在的目录开始解析
+    pub fn create_symlink(&self, name: &str, target: &str) -> Option<Arc<Inode>> {
+        if target.is_empty() {
+            return None;
+        }
+        let inode = self.create_inode(name, DiskInodeType::Symlink)?;
+        inode.write_at(0, target.as_bytes());
+        Some(inode)
+    }
+
+    //读出符号链接指向的路径，当前 inode 不是符号链接时返回 None
+    pub fn read_link(&self) -> Option<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_symlink() {
+                return None;
+            }
+            let mut target = vec![0u8; disk_inode.size as usize];
+            disk_inode.read_at(0, &mut target, &self.block_device);
+            String::from_utf8(target).ok()
+        })
+    }
+
+    //在当前目录下新建一个名为 name 的目录项，指向已有的文件或符号链接 target ，
+    //两者共享同一个 inode 。不允许为目录建立硬链接
+    pub fn link(&self, name: &str, target: &Inode) -> bool {
+        let mut fs = self.fs.lock();
+        if target.read_disk_inode(|disk_inode| disk_inode.is_dir()) || !self.can_add_entry(name) {
+            return false;
+        }
+        self.modify_disk_inode(|dir_inode| {
+            self.append_dirent(name, target.inode_id, dir_inode, &mut fs);
+        });
+        target.modify_disk_inode(|disk_inode| disk_inode.nlink += 1);
+        block_cache_sync_all();
+        true
+    }
+
+    //收集当前目录下的所有文件的文件名并以向量的形式返回
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
@@ -162,21 +306,120 @@ impl Inode {
                     disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
                     DIRENT_SZ,
                 );
-                v.push(String::from(dirent.name()));
+                if !dirent.name().is_empty() {
+                    v.push(String::from(dirent.name()));
+                }
             }
             v
         })
     }
 
+    //目录中除 . 和 .. 以外是否还有别的目录项
+    fn is_empty_dir(&self, disk_inode: &DiskInode) -> bool {
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        (0..file_count).all(|i| {
+            disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+            matches!(dirent.name(), "" | "." | "..")
+        })
+    }
+
+    //从当前目录中删除名为 name 的目录项。如果这是指向该 inode 的最后一个名字，
+    //就回收 inode 及其全部数据块。目录只有在除 . 和 .. 外为空时才能被删除。
+    //inode 被回收后仍持有它的 Inode 的使用者不应再访问它，这样的使用者应改用 unlink_entry
+    pub fn unlink(&self, name: &str) -> bool {
+        match self.unlink_entry(name) {
+            Some(inode) => {
+                if inode.nlink() == 0 {
+                    inode.reclaim();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    //从当前目录中删除名为 name 的目录项，减少 inode 的链接数并返回该 inode 。inode 并不立即回收，
+    //它的内容仍然可以读写，由调用者在链接数降为 0 且最后一个使用者释放它之后调用 reclaim
+    pub fn unlink_entry(&self, name: &str) -> Option<Arc<Inode>> {
+        if name == "." || name == ".." {
+            return None;
+        }
+        let fs = self.fs.lock();
+        //找到目录项所在的位置以及它指向的 inode 编号
+        let (slot, inode_id) = self.read_disk_inode(|dir_inode| {
+            assert!(dir_inode.is_dir());
+            let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+            let mut dirent = DirEntry::empty();
+            (0..file_count).find_map(|i| {
+                dir_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                if !name.is_empty() && dirent.name() == name {
+                    Some((i, dirent.inode_number()))
+                } else {
+                    None
+                }
+            })
+        })?;
+        let inode = self.get_inode(inode_id, &fs);
+        let is_dir = inode.read_disk_inode(|disk_inode| disk_inode.is_dir());
+        if is_dir && !inode.read_disk_inode(|disk_inode| inode.is_empty_dir(disk_inode)) {
+            return None;
+        }
+        //将目录项清空，留下的空位之后可以被新建的文件复用
+        self.modify_disk_inode(|dir_inode| {
+            let dirent = DirEntry::empty();
+            dir_inode.write_at(slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            //被删除的子目录中的 .. 也随之消失
+            if is_dir {
+                dir_inode.nlink -= 1;
+            }
+        });
+        //被删除的目录中只剩下 . 和 .. ，将它们一并清空。这样的目录可能仍是某个进程的当前工作目录，
+        //之后既不能再沿 .. 向上查找，也不能在其中新建文件
+        inode.modify_disk_inode(|disk_inode| {
+            if is_dir {
+                let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+                let dirent = DirEntry::empty();
+                for i in 0..file_count {
+                    disk_inode.write_at(i * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+                }
+                disk_inode.nlink = 0;
+            } else {
+                disk_inode.nlink -= 1;
+            }
+        });
+        block_cache_sync_all();
+        Some(inode)
+    }
+
+    //回收已经从目录中删除的 inode 占据的数据块和 inode 本身
+    pub fn reclaim(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            for data_block in disk_inode.clear_size(&self.block_device).into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+        fs.dealloc_inode(self.inode_id);
+        block_cache_sync_all();
+    }
+
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
     }
 
+    //写入之后的文件超过 MAX_FILE_SIZE 或者磁盘上没有足够的空闲数据块时什么也不写，返回 0
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let end = match offset.checked_add(buf.len()) {
+            Some(end) if end <= MAX_FILE_SIZE => end,
+            _ => return 0,
+        };
         let mut fs = self.fs.lock();
         let size = self.modify_disk_inode(|disk_inode| {
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
+            if !self.increase_size(end as u32, disk_inode, &mut fs) {
+                return 0;
+            }
             disk_inode.write_at(offset, buf, &self.block_device)
         });
         block_cache_sync_all();
@@ -3,6 +3,7 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 /// 动态内存分配中内核堆大小
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
+//初始的内核堆大小，不够时再从物理页帧分配器中扩展
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
 pub const MEMORY_END: usize = 0x80800000;
 pub const PAGE_SIZE: usize = 0x1000;
@@ -10,6 +11,12 @@ pub const PAGE_SIZE_BITS: usize = 0xc;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+//mmap 未指定地址时从这里开始寻找空闲区域
+pub const MMAP_BASE: usize = 0x1_0000_0000;
+//SV39 中用户可以使用的低半部分地址空间的上界
+pub const USER_SPACE_TOP: usize = 0x40_0000_0000;
+//主线程的用户栈放在用户地址空间的最高处，其余线程的用户栈依次向下排列，为紧跟在 ELF 数据之后的堆留出向上增长的空间
+pub const USER_STACK_TOP: usize = USER_SPACE_TOP;
 /// Clock frequency
 //可用内存大小设置为8Mb，与K210一致
 pub use crate::board::{CLOCK_FREQ, MMIO};
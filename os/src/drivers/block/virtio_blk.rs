@@ -1,11 +1,8 @@
 use super::BlockDevice;
 use crate::memory::{
-    frame_alloc, frame_dealloc, kernel_token, FrameTracker, PageTable, PhysAddr, PhysPageNum,
-    StepByOne, VirtAddr,
+    frame_alloc_contiguous, frame_dealloc_contiguous, kernel_token, PageTable, PhysAddr, VirtAddr,
 };
 use crate::sync::UPSafeCell;
-use alloc::vec::Vec;
-use lazy_static::*;
 use virtio_drivers::{VirtIOBlk, VirtIOHeader};
 
 #[allow(unused)]
@@ -13,10 +10,6 @@ const VIRTIO0: usize = 0x10001000;
 
 pub struct VirtIOBlock(UPSafeCell<VirtIOBlk<'static>>);
 
-lazy_static! {
-    static ref QUEUE_FRAMES: UPSafeCell<Vec<FrameTracker>> = unsafe { UPSafeCell::new(Vec::new()) };
-}
-
 impl BlockDevice for VirtIOBlock {
     fn read_block(&self, block_id: usize, buf: &mut [u8]) {
         self.0
@@ -43,27 +36,17 @@ impl VirtIOBlock {
     }
 }
 
+//virtio 设备通过 DMA 直接访问的内存必须在物理地址上连续
 #[no_mangle]
 pub extern "C" fn virtio_dma_alloc(pages: usize) -> PhysAddr {
-    let mut ppn_base = PhysPageNum(0);
-    for i in 0..pages {
-        let frame = frame_alloc().unwrap();
-        if i == 0 {
-            ppn_base = frame.ppn;
-        }
-        assert_eq!(frame.ppn.0, ppn_base.0 + i);
-        QUEUE_FRAMES.exclusive_access().push(frame);
-    }
-    ppn_base.into()
+    frame_alloc_contiguous(pages)
+        .expect("failed to allocate DMA memory")
+        .into()
 }
 
 #[no_mangle]
 pub extern "C" fn virtio_dma_dealloc(pa: PhysAddr, pages: usize) -> i32 {
-    let mut ppn_base: PhysPageNum = pa.into();
-    for _ in 0..pages {
-        frame_dealloc(ppn_base);
-        ppn_base.step();
-    }
+    frame_dealloc_contiguous(pa.into(), pages);
     0
 }
 
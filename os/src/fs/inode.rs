@@ -1,11 +1,14 @@
+use super::page_cache::{clear_file, page_cache, PageCache};
 use super::File;
 use crate::drivers::BLOCK_DEVICE;
 use crate::memory::UserBuffer;
 use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use easy_fs::{EasyFileSystem, Inode};
+use easy_fs::{EasyFileSystem, Inode, MAX_FILE_SIZE};
 use lazy_static::*;
 
 pub struct OSInode {
@@ -17,14 +20,26 @@ pub struct OSInode {
 pub struct OSInodeInner {
     offset: usize,
     inode: Arc<Inode>,
+    //文件内容的读写都经过页缓存
+    cache: Arc<PageCache>,
 }
 
 impl OSInode {
     pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+        *OPEN_INODES
+            .exclusive_access()
+            .entry(inode.inode_id())
+            .or_insert(0) += 1;
         Self {
             readable,
             writable,
-            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+            inner: unsafe {
+                UPSafeCell::new(OSInodeInner {
+                    offset: 0,
+                    cache: page_cache(&inode),
+                    inode,
+                })
+            },
         }
     }
     pub fn read_all(&self) -> Vec<u8> {
@@ -32,7 +47,7 @@ impl OSInode {
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
-            let len = inner.inode.read_at(inner.offset, &mut buffer);
+            let len = inner.cache.read_at(inner.offset, &mut buffer);
             if len == 0 {
                 break;
             }
@@ -43,16 +58,100 @@ impl OSInode {
     }
 }
 
+//最后一个 OSInode 被释放时，如果文件的所有名字都已经被删除，就回收它的 inode
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        let inode = self.inner.exclusive_access().inode.clone();
+        let mut open_inodes = OPEN_INODES.exclusive_access();
+        let count = open_inodes.get_mut(&inode.inode_id()).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            open_inodes.remove(&inode.inode_id());
+            drop(open_inodes);
+            if inode.nlink() == 0 {
+                inode.reclaim();
+            }
+        }
+    }
+}
+
+impl OSInodeInner {
+    //从 offset 开始依次填满用户缓冲区的各个片段，直到读到文件末尾
+    fn read_at(&self, mut offset: usize, mut buf: UserBuffer) -> usize {
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers.iter_mut() {
+            let read_size = self.cache.read_at(offset, *slice);
+            if read_size == 0 {
+                break;
+            }
+            offset += read_size;
+            total_read_size += read_size;
+        }
+        total_read_size
+    }
+    //依次写入用户缓冲区的各个片段。写入之后的文件会超过最大长度时返回 None ；
+    //磁盘空间不足时在写不下的片段处停止，一个字节也没有写入时返回 None
+    fn write_at(&self, mut offset: usize, buf: UserBuffer) -> Option<usize> {
+        let len = buf.len();
+        if offset
+            .checked_add(len)
+            .map_or(true, |end| end > MAX_FILE_SIZE)
+        {
+            return None;
+        }
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers.iter() {
+            let write_size = self.cache.write_at(offset, *slice);
+            offset += write_size;
+            total_write_size += write_size;
+            if write_size < slice.len() {
+                break;
+            }
+        }
+        if total_write_size == 0 && len > 0 {
+            return None;
+        }
+        Some(total_write_size)
+    }
+}
+
 lazy_static! {
     pub static ref ROOT_INODE: Arc<Inode> = {
         let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
         Arc::new(EasyFileSystem::root_inode(&efs))
     };
+    //每个 inode 当前被多少个 OSInode 打开，还在被打开的 inode 即使没有了名字也不能回收
+    static ref OPEN_INODES: UPSafeCell<BTreeMap<u32, usize>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+//根目录中保存交换文件的目录项只供内核使用，用户既看不到它，也不能打开、删除或者覆盖它
+const SWAP_FILE: &str = ".swap";
+
+//dir 中名为 name 的目录项是否对用户隐藏
+fn is_hidden(dir: &Inode, name: &str) -> bool {
+    name == SWAP_FILE && dir.inode_id() == ROOT_INODE.inode_id()
+}
+
+//打开交换文件，不存在时在根目录中创建。每次启动都会清空之前的交换文件
+pub fn open_swap_file() -> Arc<Inode> {
+    let inode = match ROOT_INODE.find(SWAP_FILE) {
+        Some(inode) => inode,
+        None => ROOT_INODE
+            .create(SWAP_FILE)
+            .expect("failed to create the swap file"),
+    };
+    clear_file(&inode);
+    inode
 }
 
 pub fn list_apps() {
     println!("/**** APPS ****");
-    for app in ROOT_INODE.ls() {
+    for app in ROOT_INODE
+        .ls()
+        .iter()
+        .filter(|name| *name != "." && *name != ".." && !is_hidden(&ROOT_INODE, name))
+    {
         println!("{}", app);
     }
     println!("**************/");
@@ -68,6 +167,11 @@ bitflags! {
     }
 }
 
+//lseek 中 whence 的取值：分别相对于文件开头、当前读写位置和文件末尾
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
 impl OpenFlags {
     /// Do not check validity for simplicity
     /// Return (readable, writable)
@@ -82,27 +186,202 @@ impl OpenFlags {
     }
 }
 
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+//解析一条路径时最多跟随的符号链接个数，超过时认为出现了循环（相当于 ELOOP）
+const MAX_SYMLINK_HOPS: usize = 8;
+
+//沿路径逐级查找索引节点。以 / 开头的绝对路径从根目录出发，否则从 cwd 出发；
+//路径中的 . 和 .. 由每个目录中对应的目录项自然地处理。
+//中间各级的符号链接总是被跟随，最后一级只有 follow_last 为真时才跟随。
+//符号链接中的相对路径从符号链接所在的目录开始解析，hops 记录已经跟随过的符号链接个数
+fn walk_path(
+    cwd: &Arc<Inode>,
+    path: &str,
+    follow_last: bool,
+    hops: &mut usize,
+) -> Option<Arc<Inode>> {
+    let mut inode = if path.starts_with('/') {
+        ROOT_INODE.clone()
+    } else {
+        cwd.clone()
+    };
+    let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+    for (i, name) in names.iter().enumerate() {
+        if !inode.is_dir() || is_hidden(&inode, name) {
+            return None;
+        }
+        let next = inode.find(name)?;
+        if next.is_symlink() && (follow_last || i + 1 < names.len()) {
+            if *hops == MAX_SYMLINK_HOPS {
+                return None;
+            }
+            *hops += 1;
+            let target = next.read_link()?;
+            inode = walk_path(&inode, target.as_str(), true, hops)?;
+        } else {
+            inode = next;
+        }
+    }
+    Some(inode)
+}
+
+//查找路径对应的索引节点，路径中的符号链接全部被跟随
+pub fn find_inode(cwd: &Arc<Inode>, path: &str) -> Option<Arc<Inode>> {
+    walk_path(cwd, path, true, &mut 0)
+}
+
+//查找路径对应的索引节点，路径最后一级是符号链接时返回符号链接本身
+pub fn find_inode_nofollow(cwd: &Arc<Inode>, path: &str) -> Option<Arc<Inode>> {
+    walk_path(cwd, path, false, &mut 0)
+}
+
+//将路径拆分为其所在目录的路径和最后一级的名字，如 "a/b/c" -> ("a/b", "c")
+fn split_path(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+//找到路径最后一级所在的目录，并返回该目录和最后一级的名字
+fn find_parent<'a>(cwd: &Arc<Inode>, path: &'a str) -> Option<(Arc<Inode>, &'a str)> {
+    let (parent_path, name) = split_path(path);
+    let parent = find_inode(cwd, parent_path)?;
+    if parent.is_dir() {
+        Some((parent, name))
+    } else {
+        None
+    }
+}
+
+pub fn open_file(cwd: &Arc<Inode>, path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     let (readable, writable) = flags.read_write();
     if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
+        let (parent, name) = find_parent(cwd, path)?;
+        //已经存在的文件可能是一个符号链接，需要打开它指向的文件
+        if let Some(inode) = find_inode(cwd, path) {
+            if inode.is_dir() {
+                return None;
+            }
             // clear size
-            inode.clear();
+            clear_file(&inode);
             Some(Arc::new(OSInode::new(readable, writable, inode)))
+        } else if parent.find(name).is_some() {
+            //名字已经被一个悬空或者循环的符号链接占用
+            None
         } else {
             // create file
-            ROOT_INODE
+            parent
                 .create(name)
                 .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
         }
     } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
+        let inode = find_inode(cwd, path)?;
+        //目录只能以只读方式打开，用作 *at 系列系统调用的起点
+        if inode.is_dir() {
+            if writable || flags.contains(OpenFlags::TRUNC) {
+                return None;
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+            return Some(Arc::new(OSInode::new(false, false, inode)));
+        }
+        if flags.contains(OpenFlags::TRUNC) {
+            clear_file(&inode);
+        }
+        Some(Arc::new(OSInode::new(readable, writable, inode)))
+    }
+}
+
+//创建一个新目录，路径中除最后一级外的各级目录都必须已经存在
+pub fn create_dir(cwd: &Arc<Inode>, path: &str) -> Option<Arc<Inode>> {
+    let (parent, name) = find_parent(cwd, path)?;
+    parent.create_dir(name)
+}
+
+//删除路径对应的目录项。is_dir 为真时只删除空目录，否则只删除普通文件。
+//最后一个名字被删除后，如果该文件没有被任何进程打开就立即回收其索引节点，否则等到它被最后关闭时再回收
+pub fn unlink_file(cwd: &Arc<Inode>, path: &str, is_dir: bool) -> bool {
+    let (parent, name) = match find_parent(cwd, path) {
+        Some(found) => found,
+        None => return false,
+    };
+    match parent.find(name) {
+        Some(inode) if inode.is_dir() == is_dir && !is_hidden(&parent, name) => {}
+        _ => return false,
+    }
+    let inode = match parent.unlink_entry(name) {
+        Some(inode) => inode,
+        None => return false,
+    };
+    if inode.nlink() == 0
+        && !OPEN_INODES
+            .exclusive_access()
+            .contains_key(&inode.inode_id())
+    {
+        inode.reclaim();
+    }
+    true
+}
+
+//为 old_path 指向的文件建立一个新的名字 new_path ，new_path 所在的目录必须已经存在。
+//old_path 是符号链接时链接的是符号链接本身
+pub fn link_file(
+    old_cwd: &Arc<Inode>,
+    old_path: &str,
+    new_cwd: &Arc<Inode>,
+    new_path: &str,
+) -> bool {
+    let target = match find_inode_nofollow(old_cwd, old_path) {
+        Some(inode) => inode,
+        None => return false,
+    };
+    match find_parent(new_cwd, new_path) {
+        Some((parent, name)) => parent.link(name, &target),
+        None => false,
+    }
+}
+
+//在 path 处创建一个指向 target 的符号链接
+pub fn create_symlink(cwd: &Arc<Inode>, target: &str, path: &str) -> bool {
+    match find_parent(cwd, path) {
+        Some((parent, name)) => parent.create_symlink(name, target).is_some(),
+        None => false,
+    }
+}
+
+//读出 path 处的符号链接指向的路径
+pub fn read_symlink(cwd: &Arc<Inode>, path: &str) -> Option<String> {
+    find_inode_nofollow(cwd, path)?.read_link()
+}
+
+//从 cwd 沿 .. 逐级向上走到根目录，在每一级父目录中找到当前目录的名字，拼出绝对路径。
+//路径上的某个目录已经被删除时返回 None
+pub fn dir_path(cwd: &Arc<Inode>) -> Option<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut inode = cwd.clone();
+    while inode.inode_id() != ROOT_INODE.inode_id() {
+        let parent = inode.find("..")?;
+        let name = parent
+            .ls()
+            .into_iter()
+            .filter(|name| name != "." && name != "..")
+            .find(|name| {
+                parent
+                    .find(name)
+                    .map_or(false, |child| child.inode_id() == inode.inode_id())
+            })?;
+        names.push(name);
+        inode = parent;
+    }
+    let mut path = String::new();
+    for name in names.iter().rev() {
+        path.push('/');
+        path.push_str(name);
+    }
+    if path.is_empty() {
+        path.push('/');
     }
+    Some(path)
 }
 
 impl File for OSInode {
@@ -112,28 +391,43 @@ impl File for OSInode {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, mut buf: UserBuffer) -> usize {
+    fn read(&self, buf: UserBuffer) -> Option<usize> {
         let mut inner = self.inner.exclusive_access();
-        let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, *slice);
-            if read_size == 0 {
-                break;
-            }
-            inner.offset += read_size;
-            total_read_size += read_size;
-        }
-        total_read_size
+        let read_size = inner.read_at(inner.offset, buf);
+        inner.offset += read_size;
+        Some(read_size)
     }
-    fn write(&self, buf: UserBuffer) -> usize {
+    fn write(&self, buf: UserBuffer) -> Option<usize> {
         let mut inner = self.inner.exclusive_access();
-        let mut total_write_size = 0usize;
-        for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
-        }
-        total_write_size
+        let write_size = inner.write_at(inner.offset, buf)?;
+        inner.offset += write_size;
+        Some(write_size)
+    }
+    fn seek(&self, offset: isize, whence: usize) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => inner.offset as isize,
+            SEEK_END => inner.inode.size() as isize,
+            _ => return None,
+        };
+        //允许移动到文件末尾之后，之后的写入会用 0 填充中间的空洞，但不能超过文件的最大长度
+        let new_offset = base
+            .checked_add(offset)
+            .filter(|offset| *offset >= 0 && *offset as usize <= MAX_FILE_SIZE)?;
+        inner.offset = new_offset as usize;
+        Some(inner.offset)
+    }
+    fn read_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        Some(self.inner.exclusive_access().read_at(offset, buf))
+    }
+    fn write_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        self.inner.exclusive_access().write_at(offset, buf)
+    }
+    fn inode(&self) -> Option<Arc<Inode>> {
+        Some(self.inner.exclusive_access().inode.clone())
+    }
+    fn page_cache(&self) -> Option<Arc<PageCache>> {
+        Some(self.inner.exclusive_access().cache.clone())
     }
 }
@@ -1,14 +1,53 @@
 mod inode;
+mod page_cache;
+mod pipe;
+mod stat;
 mod stdio;
 
 use crate::memory::UserBuffer;
+use alloc::sync::Arc;
+use easy_fs::Inode;
 
 pub trait File: Send + Sync {
     fn readable(&self) -> bool;
     fn writable(&self) -> bool;
-    fn read(&self, buf: UserBuffer) -> usize;
-    fn write(&self, buf: UserBuffer) -> usize;
+    //返回读写的字节数。等待数据时被信号打断、一个字节也没有读写时返回 None
+    fn read(&self, buf: UserBuffer) -> Option<usize>;
+    fn write(&self, buf: UserBuffer) -> Option<usize>;
+    //文件背后的 easy-fs 索引节点，标准输入输出等没有索引节点的文件返回 None
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+    //普通文件的页缓存，文件映射通过它访问文件的内容
+    fn page_cache(&self) -> Option<Arc<PageCache>> {
+        None
+    }
+    //按照 whence 移动文件的读写位置并返回新的位置，不支持随机访问的文件返回 None
+    fn seek(&self, _offset: isize, _whence: usize) -> Option<usize> {
+        None
+    }
+    //从 offset 处开始读取，不改变文件的读写位置。不支持随机访问的文件返回 None
+    fn read_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    //从 offset 处开始写入，不改变文件的读写位置。不支持随机访问的文件返回 None
+    fn write_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    //文件的元数据，有索引节点的文件从索引节点中读取，否则视为字符设备
+    fn stat(&self) -> Stat {
+        match self.inode() {
+            Some(inode) => Stat::from_inode(&inode),
+            None => Stat::char_device(),
+        }
+    }
 }
 
-pub use inode::{list_apps, open_file, OSInode, OpenFlags};
+pub use inode::{
+    create_dir, create_symlink, dir_path, find_inode, find_inode_nofollow, link_file, list_apps,
+    open_file, open_swap_file, read_symlink, unlink_file, OSInode, OpenFlags, ROOT_INODE,
+};
+pub use page_cache::{reclaim_page_cache, PageCache};
+pub use pipe::{make_pipe, Pipe};
+pub use stat::{Stat, StatMode};
 pub use stdio::{Stdin, Stdout};
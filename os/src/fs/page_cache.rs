@@ -0,0 +1,167 @@
+//! 以页面为单位缓存普通文件的内容。文件的读写和文件映射都经过同一个页缓存，因此总能看到相同的数据
+use crate::config::PAGE_SIZE;
+use crate::memory::{frame_alloc, FrameTracker};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use easy_fs::Inode;
+use lazy_static::*;
+
+//一个文件的页缓存，第 index 个页面缓存文件中 [index * PAGE_SIZE, (index + 1) * PAGE_SIZE) 的内容。
+//文件的写入总是直接写穿到磁盘上，只有共享文件映射中被用户写过的页面需要在 msync/munmap/退出时写回，
+//因此没有被任何地址空间映射的页面总是干净的，物理内存不足时可以直接丢弃
+pub struct PageCache {
+    inode: Arc<Inode>,
+    pages: UPSafeCell<BTreeMap<usize, Arc<FrameTracker>>>,
+}
+
+impl PageCache {
+    //缓存中的第 index 个页面
+    pub fn get(&self, index: usize) -> Option<Arc<FrameTracker>> {
+        self.pages.exclusive_access().get(&index).cloned()
+    }
+    //用文件的内容填充物理页帧，并将它作为第 index 个页面加入缓存，超出文件末尾的部分保持为 0
+    pub fn fill(&self, index: usize, frame: FrameTracker) -> Arc<FrameTracker> {
+        self.inode
+            .read_at(index * PAGE_SIZE, frame.ppn.get_bytes_array());
+        let page = Arc::new(frame);
+        self.pages.exclusive_access().insert(index, page.clone());
+        page
+    }
+    //丢弃一个没有被映射的页面，返回是否找到了这样的页面。
+    //页缓存正在被使用（比如正在为它分配物理页帧）时不做任何事
+    fn reclaim_one(&self) -> bool {
+        let mut pages = match self.pages.try_exclusive_access() {
+            Some(pages) => pages,
+            None => return false,
+        };
+        match pages
+            .iter()
+            .find(|(_, page)| Arc::strong_count(page) == 1)
+            .map(|(index, _)| *index)
+        {
+            Some(index) => {
+                pages.remove(&index);
+                true
+            }
+            None => false,
+        }
+    }
+    //从 offset 开始读取文件到 buf 中，返回读到的字节数。缓存中没有的页面先从磁盘读入缓存，
+    //物理内存不足时直接读取磁盘
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let size = self.inode.size() as usize;
+        if offset >= size {
+            return 0;
+        }
+        let end = size.min(offset + buf.len());
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE;
+            let page_end = end.min((index + 1) * PAGE_SIZE);
+            let dst = &mut buf[pos - offset..page_end - offset];
+            match self
+                .get(index)
+                .or_else(|| frame_alloc().map(|frame| self.fill(index, frame)))
+            {
+                Some(page) => {
+                    let page_offset = pos % PAGE_SIZE;
+                    dst.copy_from_slice(
+                        &page.ppn.get_bytes_array()[page_offset..page_offset + dst.len()],
+                    );
+                }
+                None => {
+                    self.inode.read_at(pos, dst);
+                }
+            }
+            pos = page_end;
+        }
+        end - offset
+    }
+    //从 offset 开始将 buf 写入文件，同时更新缓存中对应的页面
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let size = self.inode.write_at(offset, buf);
+        let end = offset + size;
+        let pages = self.pages.exclusive_access();
+        for (index, page) in pages.range(offset / PAGE_SIZE..(end + PAGE_SIZE - 1) / PAGE_SIZE) {
+            let start = offset.max(index * PAGE_SIZE);
+            let stop = end.min((index + 1) * PAGE_SIZE);
+            let page_offset = start % PAGE_SIZE;
+            page.ppn.get_bytes_array()[page_offset..page_offset + stop - start]
+                .copy_from_slice(&buf[start - offset..stop - offset]);
+        }
+        size
+    }
+    //将第 index 个页面写回磁盘。只写回文件大小以内的部分，不会改变文件的大小
+    pub fn write_back(&self, index: usize) {
+        let page = match self.get(index) {
+            Some(page) => page,
+            None => return,
+        };
+        let size = self.inode.size() as usize;
+        let start = index * PAGE_SIZE;
+        if start >= size {
+            return;
+        }
+        let len = PAGE_SIZE.min(size - start);
+        self.inode
+            .write_at(start, &page.ppn.get_bytes_array()[..len]);
+    }
+}
+
+//页缓存被释放时从全局的表中去掉它
+impl Drop for PageCache {
+    fn drop(&mut self) {
+        PAGE_CACHES
+            .exclusive_access()
+            .remove(&self.inode.inode_id());
+    }
+}
+
+lazy_static! {
+    //每个 inode 对应的页缓存。页缓存由打开文件的 OSInode 和映射文件的逻辑段持有，这里只保存弱引用，
+    //没有人使用的页缓存会被立即释放
+    static ref PAGE_CACHES: UPSafeCell<BTreeMap<u32, Weak<PageCache>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+//获取 inode 的页缓存，还没有时就新建一个
+pub fn page_cache(inode: &Arc<Inode>) -> Arc<PageCache> {
+    let mut caches = PAGE_CACHES.exclusive_access();
+    if let Some(cache) = caches
+        .get(&inode.inode_id())
+        .and_then(|cache| cache.upgrade())
+    {
+        return cache;
+    }
+    let cache = Arc::new(PageCache {
+        inode: inode.clone(),
+        pages: unsafe { UPSafeCell::new(BTreeMap::new()) },
+    });
+    caches.insert(inode.inode_id(), Arc::downgrade(&cache));
+    cache
+}
+
+//物理内存不足时从某个页缓存中回收一个干净的页面，返回 false 说明没有可以回收的页面
+pub fn reclaim_page_cache() -> bool {
+    let caches: Vec<Arc<PageCache>> = match PAGE_CACHES.try_exclusive_access() {
+        Some(caches) => caches.values().filter_map(|cache| cache.upgrade()).collect(),
+        None => return false,
+    };
+    caches.iter().any(|cache| cache.reclaim_one())
+}
+
+//清空文件的内容，已经缓存的页面也随之清零
+pub fn clear_file(inode: &Arc<Inode>) {
+    inode.clear();
+    let cache = PAGE_CACHES
+        .exclusive_access()
+        .get(&inode.inode_id())
+        .and_then(|cache| cache.upgrade());
+    if let Some(cache) = cache {
+        for page in cache.pages.exclusive_access().values() {
+            page.ppn.get_bytes_array().fill(0);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use super::{File, Stat, StatMode};
+use crate::memory::UserBuffer;
+use crate::sync::UPSafeCell;
+use alloc::sync::{Arc, Weak};
+
+use crate::task::{current_signal_pending, suspend_current_and_run_next};
+
+//管道的一端，读端和写端共享同一个环形缓冲区
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+}
+
+impl Pipe {
+    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: true,
+            writable: false,
+            buffer,
+        }
+    }
+    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: false,
+            writable: true,
+            buffer,
+        }
+    }
+}
+
+const RING_BUFFER_SIZE: usize = 32;
+
+#[derive(Copy, Clone, PartialEq)]
+enum RingBufferStatus {
+    Full,
+    Empty,
+    Normal,
+}
+
+pub struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,//下一个要读出的字节的位置
+    tail: usize,//下一个要写入的字节的位置
+    status: RingBufferStatus,
+    //只保存读端和写端的弱引用，两端各自被所有文件描述符释放之后，另一端可以通过它们得知
+    read_end: Option<Weak<Pipe>>,
+    write_end: Option<Weak<Pipe>>,
+}
+
+impl PipeRingBuffer {
+    pub fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            tail: 0,
+            status: RingBufferStatus::Empty,
+            read_end: None,
+            write_end: None,
+        }
+    }
+    pub fn set_read_end(&mut self, read_end: &Arc<Pipe>) {
+        self.read_end = Some(Arc::downgrade(read_end));
+    }
+    pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
+        self.write_end = Some(Arc::downgrade(write_end));
+    }
+    pub fn write_byte(&mut self, byte: u8) {
+        self.status = RingBufferStatus::Normal;
+        self.arr[self.tail] = byte;
+        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
+    }
+    pub fn read_byte(&mut self) -> u8 {
+        self.status = RingBufferStatus::Normal;
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        if self.head == self.tail {
+            self.status = RingBufferStatus::Empty;
+        }
+        c
+    }
+    //缓冲区中还能读出的字节数
+    pub fn available_read(&self) -> usize {
+        if self.status == RingBufferStatus::Empty {
+            0
+        } else if self.tail > self.head {
+            self.tail - self.head
+        } else {
+            self.tail + RING_BUFFER_SIZE - self.head
+        }
+    }
+    //缓冲区中还能写入的字节数
+    pub fn available_write(&self) -> usize {
+        if self.status == RingBufferStatus::Full {
+            0
+        } else {
+            RING_BUFFER_SIZE - self.available_read()
+        }
+    }
+    //所有的写端都已经关闭，读完缓冲区中剩下的内容之后就读到了文件末尾
+    pub fn all_write_ends_closed(&self) -> bool {
+        self.write_end.as_ref().unwrap().upgrade().is_none()
+    }
+    //所有的读端都已经关闭，再写入的内容不会有人读到
+    pub fn all_read_ends_closed(&self) -> bool {
+        self.read_end.as_ref().unwrap().upgrade().is_none()
+    }
+}
+
+//创建一个管道并返回它的读端和写端
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
+    buffer.exclusive_access().set_read_end(&read_end);
+    buffer.exclusive_access().set_write_end(&write_end);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    //缓冲区为空时让出 CPU 等待写端写入。读到了一些内容之后缓冲区再次变空，或者所有写端都已经关闭时，
+    //不再等待，返回已经读到的字节数。等待期间收到信号时返回 None
+    fn read(&self, buf: UserBuffer) -> Option<usize> {
+        assert!(self.readable());
+        let want_to_read = buf.len();
+        if want_to_read == 0 {
+            return Some(0);
+        }
+        let mut buf_iter = buf.into_iter();
+        let mut already_read = 0usize;
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            let loop_read = ring_buffer.available_read();
+            if loop_read == 0 {
+                if already_read > 0 || ring_buffer.all_write_ends_closed() {
+                    return Some(already_read);
+                }
+                drop(ring_buffer);
+                if current_signal_pending() {
+                    return None;
+                }
+                suspend_current_and_run_next();
+                continue;
+            }
+            for _ in 0..loop_read {
+                if let Some(byte_ref) = buf_iter.next() {
+                    unsafe {
+                        *byte_ref = ring_buffer.read_byte();
+                    }
+                    already_read += 1;
+                    if already_read == want_to_read {
+                        return Some(want_to_read);
+                    }
+                } else {
+                    return Some(already_read);
+                }
+            }
+        }
+    }
+    //缓冲区已满时让出 CPU 等待读端读出，直到写完用户缓冲区，或者所有读端都关闭时返回已经写入的字节数。
+    //等待期间收到信号时返回已经写入的字节数，一个字节也没有写入时返回 None
+    fn write(&self, buf: UserBuffer) -> Option<usize> {
+        assert!(self.writable());
+        let want_to_write = buf.len();
+        if want_to_write == 0 {
+            return Some(0);
+        }
+        let mut buf_iter = buf.into_iter();
+        let mut already_write = 0usize;
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.all_read_ends_closed() {
+                return Some(already_write);
+            }
+            let loop_write = ring_buffer.available_write();
+            if loop_write == 0 {
+                drop(ring_buffer);
+                if current_signal_pending() {
+                    return if already_write > 0 {
+                        Some(already_write)
+                    } else {
+                        None
+                    };
+                }
+                suspend_current_and_run_next();
+                continue;
+            }
+            for _ in 0..loop_write {
+                if let Some(byte_ref) = buf_iter.next() {
+                    ring_buffer.write_byte(unsafe { *byte_ref });
+                    already_write += 1;
+                    if already_write == want_to_write {
+                        return Some(want_to_write);
+                    }
+                } else {
+                    return Some(already_write);
+                }
+            }
+        }
+    }
+    fn stat(&self) -> Stat {
+        let mut stat = Stat::char_device();
+        stat.mode = StatMode::FIFO;
+        stat
+    }
+}
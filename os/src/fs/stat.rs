@@ -0,0 +1,63 @@
+use bitflags::*;
+use easy_fs::Inode;
+
+//easy-fs 所在块设备的设备号，目前内核中只有这一个文件系统
+const EASY_FS_DEV: u64 = 0;
+
+bitflags! {
+    //文件类型，取值与 Linux 中 st_mode 的高位相同
+    pub struct StatMode: u32 {
+        const NULL = 0;
+        //管道
+        const FIFO = 0o010000;
+        //字符设备，如标准输入输出
+        const CHR = 0o020000;
+        const DIR = 0o040000;
+        const FILE = 0o100000;
+        const LINK = 0o120000;
+    }
+}
+
+//fstat/fstatat 返回给用户的文件元数据
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    pub dev: u64,//文件所在的设备号
+    pub ino: u64,//inode 编号
+    pub mode: StatMode,//文件类型
+    pub nlink: u32,//硬链接数
+    pub size: u64,//文件内容的字节数
+    pub blocks: u64,//文件占据的 512 字节块数，包括索引块
+}
+
+impl Stat {
+    //没有索引节点的字符设备
+    pub fn char_device() -> Self {
+        Self {
+            dev: 0,
+            ino: 0,
+            mode: StatMode::CHR,
+            nlink: 1,
+            size: 0,
+            blocks: 0,
+        }
+    }
+
+    pub fn from_inode(inode: &Inode) -> Self {
+        let mode = if inode.is_dir() {
+            StatMode::DIR
+        } else if inode.is_symlink() {
+            StatMode::LINK
+        } else {
+            StatMode::FILE
+        };
+        Self {
+            dev: EASY_FS_DEV,
+            ino: inode.inode_id() as u64,
+            mode,
+            nlink: inode.nlink(),
+            size: inode.size() as u64,
+            blocks: inode.blocks() as u64,
+        }
+    }
+}
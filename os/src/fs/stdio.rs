@@ -1,7 +1,7 @@
 use super::File;
 use crate::memory::UserBuffer;
 use crate::sbi::console_getchar;
-use crate::task::suspend_current_and_run_next;
+use crate::task::{current_signal_pending, suspend_current_and_run_next};
 
 pub struct Stdin;
 
@@ -14,13 +14,17 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
+    fn read(&self, mut user_buf: UserBuffer) -> Option<usize> {
         assert_eq!(user_buf.len(), 1);
         // busy loop
         let mut c: usize;
         loop {
             c = console_getchar();
             if c == 0 {
+                //还没有输入时收到信号就不再等待，返回用户态去处理它
+                if current_signal_pending() {
+                    return None;
+                }
                 suspend_current_and_run_next();
                 continue;
             } else {
@@ -31,9 +35,9 @@ impl File for Stdin {
         unsafe {
             user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
         }
-        1
+        Some(1)
     }
-    fn write(&self, _user_buf: UserBuffer) -> usize {
+    fn write(&self, _user_buf: UserBuffer) -> Option<usize> {
         panic!("Cannot write to stdin!");
     }
 }
@@ -45,13 +49,13 @@ impl File for Stdout {
     fn writable(&self) -> bool {
         true
     }
-    fn read(&self, _user_buf: UserBuffer) -> usize {
+    fn read(&self, _user_buf: UserBuffer) -> Option<usize> {
         panic!("Cannot read from stdout!");
     }
-    fn write(&self, user_buf: UserBuffer) -> usize {
+    fn write(&self, user_buf: UserBuffer) -> Option<usize> {
         for buffer in user_buf.buffers.iter() {
             print!("{}", core::str::from_utf8(*buffer).unwrap());
         }
-        user_buf.len()
+        Some(user_buf.len())
     }
 }
@@ -1,8 +1,11 @@
 use super::{PhysAddr, PhysPageNum};
 use crate::config::MEMORY_END;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
+use crate::fs::reclaim_page_cache;
 use crate::sync::UPSafeCell;
+use crate::task::swap_out_any;
 use lazy_static::*;
 use crate::println;
 
@@ -39,73 +42,135 @@ impl Drop for FrameTracker {
 
 //创建一个物理页帧管理器的实例，以物理页号为单位进行物理页帧的分配和回收。
 trait FrameAllocator {
-    fn new() -> Self;    
+    fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
+    //分配 pages 个物理地址连续的物理页帧，返回第一个物理页号
+    fn alloc_contiguous(&mut self, pages: usize) -> Option<PhysPageNum>;
     fn dealloc(&mut self, ppn: PhysPageNum);
+    //回收从 ppn 开始的 pages 个连续的物理页帧
+    fn dealloc_contiguous(&mut self, ppn: PhysPageNum, pages: usize);
 }
 
-//最简单的栈式物理页帧管理策略
-pub struct StackFrameAllocator {
-    current: usize,  //空闲内存的起始物理页号
-    end: usize,      //空闲内存use spin::Mutex;的结束物理页号
-    recycled: Vec<usize>,//向量recycled以后入先出的方式保存了被回收的物理页
+//伙伴系统物理页帧管理策略：可用的物理页帧被划分为大小为 2 的幂的块，大小为 2^k 个页面的块的起始位置
+//（相对于第一个可用的物理页号）按 2^k 对齐，它和紧挨着的另一半合起来构成一个 2^(k+1) 个页面的块。
+//分配时从足够大的最小空闲块中切出所需的部分，回收时只要伙伴块也空闲就不断合并，两者都只需 O(log n) 时间
+pub struct BuddyFrameAllocator {
+    base: usize,  //第一个可用的物理页号
+    pages: usize, //可用的物理页帧个数
+    //free_lists[k] 按起始位置保存了所有大小为 2^k 个页面的空闲块
+    free_lists: Vec<BTreeSet<usize>>,
+    free: usize,  //空闲的物理页帧个数
 }
 
-impl StackFrameAllocator {
-    //在使用之前需要调用init方法将自身的[current, end)初始化为可用物理页号区间
+impl BuddyFrameAllocator {
+    //在使用之前需要调用init方法将物理页号区间[l, r)加入管理器
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
-        self.current = l.0;
-        self.end = r.0;
-        println!("last {} Physical Frames.", self.end - self.current);
+        self.base = l.0;
+        self.pages = r.0 - l.0;
+        let max_order = usize::BITS as usize - self.pages.leading_zeros() as usize;
+        self.free_lists = (0..max_order.max(1)).map(|_| BTreeSet::new()).collect();
+        self.insert_range(0, self.pages);
+        println!("last {} Physical Frames.", self.pages);
+    }
+    //将 [start, end) 拆成尽可能大的对齐的块放回空闲链表，start 和 end 都是相对于 base 的位置
+    fn insert_range(&mut self, mut start: usize, end: usize) {
+        while start < end {
+            let mut order = start.trailing_zeros().min(self.free_lists.len() as u32 - 1) as usize;
+            while start + (1 << order) > end {
+                order -= 1;
+            }
+            self.free_block(start, order);
+            start += 1 << order;
+        }
+    }
+    //分配一个 2^order 个页面的块，返回它相对于 base 的起始位置
+    fn alloc_block(&mut self, order: usize) -> Option<usize> {
+        let found = (order..self.free_lists.len()).find(|k| !self.free_lists[*k].is_empty())?;
+        let block = *self.free_lists[found].iter().next().unwrap();
+        self.free_lists[found].remove(&block);
+        self.free -= 1 << order;
+        //把多余的部分逐级对半拆开，后一半放回空闲链表
+        for k in (order..found).rev() {
+            self.free_lists[k].insert(block + (1 << k));
+        }
+        Some(block)
+    }
+    //回收一个 2^order 个页面的块，伙伴块也空闲时合并成更大的块
+    fn free_block(&mut self, mut block: usize, mut order: usize) {
+        if self.overlaps_free(block, order) {
+            panic!("Frame ppn={:#x} has not been allocated!", self.base + block);
+        }
+        self.free += 1 << order;
+        while order + 1 < self.free_lists.len() {
+            let buddy = block ^ (1 << order);
+            if !self.free_lists[order].remove(&buddy) {
+                break;
+            }
+            block = block.min(buddy);
+            order += 1;
+        }
+        self.free_lists[order].insert(block);
+    }
+    //块 [block, block + 2^order) 是否与某个空闲块重叠：它本身或者包含它的某个更大的块已经空闲，
+    //或者它内部有更小的块已经空闲，这两种情况都说明其中的页面被重复回收了
+    fn overlaps_free(&self, block: usize, order: usize) -> bool {
+        self.free_lists.iter().enumerate().any(|(k, free_list)| {
+            if k >= order {
+                free_list.contains(&(block & !((1 << k) - 1)))
+            } else {
+                free_list.range(block..block + (1 << order)).next().is_some()
+            }
+        })
+    }
+    //将物理页号转换为相对于 base 的位置，并检查它确实属于管理器管理的区间
+    fn offset(&self, ppn: PhysPageNum, pages: usize) -> usize {
+        if ppn.0 < self.base || ppn.0 + pages > self.base + self.pages {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn.0);
+        }
+        ppn.0 - self.base
     }
 }
 
-impl FrameAllocator for StackFrameAllocator {
+impl FrameAllocator for BuddyFrameAllocator {
     fn new() -> Self {
-        //初始化：将区间两端均设为0，然后创建一个新的向量
+        //初始化：区间为空，空闲链表在 init 时根据区间的大小创建
         Self {
-            current : 0,
-            end     : 0,
-            recycled   : Vec::new()
+            base: 0,
+            pages: 0,
+            free_lists: Vec::new(),
+            free: 0,
         }
     }
-
     fn alloc(&mut self) -> Option<PhysPageNum> {
-        //检查栈recycled内有没有之前回收的物理页号，如果有的话直接弹出栈顶并返回
-        if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else {
-            //否则的话我们只能从之前从未分配过的物理页号区间[current,end)上进行分配
-            //极端情况下可能出现内存耗尽分配失败，返回None
-            if self.current == self.end {
-                None
-            } else {
-                self.current += 1;//将管理器内部维护的current加1代表current已被分配
-                Some((self.current - 1).into())//使用into方法将usize转换成物理页号PhysPageNum
-            }
+        self.alloc_block(0).map(|block| (self.base + block).into())
+    }
+    fn alloc_contiguous(&mut self, pages: usize) -> Option<PhysPageNum> {
+        if pages == 0 {
+            return None;
+        }
+        let order = pages.next_power_of_two().trailing_zeros() as usize;
+        if order >= self.free_lists.len() {
+            return None;
         }
+        let block = self.alloc_block(order)?;
+        //块中超出 pages 的部分立即归还
+        self.insert_range(block + pages, block + (1 << order));
+        Some((self.base + block).into())
     }
     fn dealloc(&mut self, ppn: PhysPageNum) {
-        let ppn = ppn.0;
-        //检查回收页面的合法性
-        //1.该页面之前一定被分配出去过，因此它的物理页号一定<current
-        //2.该页面未处在回收状态，即它的物理页号不能在栈recycled中找到
-        if ppn >= self.current || self.recycled
-            .iter()//获取栈上内容的迭代器
-            .find(|&v| {*v == ppn})//寻找一个与输入物理页号相同的元素
-            .is_some() {//若找到说明内核其他部分实现有误，直接报错退出
-            panic!("Frame ppn={:#x} has not been allocated!", ppn);
-        }
-        // 将其压入recycled栈
-        self.recycled.push(ppn);
-        
+        let block = self.offset(ppn, 1);
+        self.free_block(block, 0);
+    }
+    fn dealloc_contiguous(&mut self, ppn: PhysPageNum, pages: usize) {
+        let start = self.offset(ppn, pages);
+        self.insert_range(start, start + pages);
     }
 }
 
-//使用UPSafeCell<T>来包裹栈式物理页帧分配器
-type FrameAllocatorImpl = StackFrameAllocator;
+//使用UPSafeCell<T>来包裹伙伴系统物理页帧分配器
+type FrameAllocatorImpl = BuddyFrameAllocator;
 lazy_static! {
-    /// Lazy initialized instance of the frame allocator implementation. Currently using StackFrameAllocator.
+    /// Lazy initialized instance of the frame allocator implementation. Currently using BuddyFrameAllocator.
     pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
     unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
 }
@@ -124,11 +189,33 @@ pub fn init_frame_allocator() {
 //其他内核模块调用的分配/回收物理页帧的接口
 //RALL思想：将一个物理页帧的生命周期绑定到一个 FrameTracker 变量上，当一个
 //FrameTracker被创建的时候，我们需要从FRAME_ALLOCATOR中分配一个物理页帧
+//物理内存不足时先回收页缓存中的干净页面或者从用户地址空间中换出页面再重试，
+//只有两者都做不到时才返回 None
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+    loop {
+        let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+        let ppn = if allocator.free > HEAP_RESERVED_PAGES {
+            allocator.alloc()
+        } else {
+            None
+        };
+        drop(allocator);
+        if let Some(ppn) = ppn {
+            return Some(FrameTracker::new(ppn));
+        }
+        if !reclaim_frame() {
+            return None;
+        }
+    }
+}
+
+//为内核堆保留的物理页帧数。扩展内核堆可能发生在任何上下文中，这时不能回收页缓存或者换出页面，
+//所以其它分配在空闲的物理页帧只剩下这么多时就先腾出页帧，被内核堆用掉的部分也随之补回来
+const HEAP_RESERVED_PAGES: usize = 64;
+
+//腾出一个物理页帧：丢弃页缓存中的干净页面不需要写磁盘，所以优先于换出用户页面
+fn reclaim_frame() -> bool {
+    reclaim_page_cache() || swap_out_any()
 }
 
 pub fn frame_dealloc(ppn: PhysPageNum) {
@@ -137,6 +224,50 @@ pub fn frame_dealloc(ppn: PhysPageNum) {
         .dealloc(ppn);
 }
 
+//分配 pages 个物理地址连续且已经清零的物理页帧，返回第一个物理页号。
+//它们不受 FrameTracker 管理，需要调用 frame_dealloc_contiguous 回收，主要供设备驱动分配 DMA 内存使用
+pub fn frame_alloc_contiguous(pages: usize) -> Option<PhysPageNum> {
+    let ppn = loop {
+        let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+        let ppn = if allocator.free >= HEAP_RESERVED_PAGES + pages {
+            allocator.alloc_contiguous(pages)
+        } else {
+            None
+        };
+        drop(allocator);
+        if let Some(ppn) = ppn {
+            break ppn;
+        }
+        if !reclaim_frame() {
+            return None;
+        }
+    };
+    for i in 0..pages {
+        PhysPageNum(ppn.0 + i).get_bytes_array().fill(0);
+    }
+    Some(ppn)
+}
+
+pub fn frame_dealloc_contiguous(ppn: PhysPageNum, pages: usize) {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .dealloc_contiguous(ppn, pages);
+}
+
+//为内核堆分配 pages 个连续的物理页帧，不清零，可以用到为内核堆保留的物理页帧。
+//物理页帧分配器自身在堆上分配内存时也可能需要扩展堆，这时分配器正在被使用，直接返回 None
+pub fn heap_frames_alloc(pages: usize) -> Option<PhysPageNum> {
+    FRAME_ALLOCATOR
+        .try_exclusive_access()?
+        .alloc_contiguous(pages)
+}
+
+//物理页帧的总数和其中空闲的个数
+pub fn frame_stats() -> (usize, usize) {
+    let allocator = FRAME_ALLOCATOR.exclusive_access();
+    (allocator.pages, allocator.free)
+}
+
 //从其他内核模块的视角看来，物理页帧分配的接口是调用frame_alloc函数得到一个
 //FrameTracker（如果物理内存还有剩余），它就代表了一个物理页帧，当它的生命
 //周期结束之后它所控制的物理页帧将被自动回收
@@ -155,6 +286,9 @@ pub fn frame_allocator_test() {
         v.push(frame);
     }
     drop(v);
+    let ppn = frame_alloc_contiguous(3).unwrap();
+    println!("contiguous frames: PPN={:#x}..{:#x}", ppn.0, ppn.0 + 3);
+    frame_dealloc_contiguous(ppn, 3);
     println!("frame_allocator_test passed!");
 }
 
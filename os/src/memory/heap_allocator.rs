@@ -1,30 +1,115 @@
 //初始化动态内存分配器
+use super::frame_allocator::heap_frames_alloc;
+use super::PhysAddr;
 use buddy_system_allocator::LockedHeap;
-use crate::config::KERNEL_HEAP_SIZE;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::config::{KERNEL_HEAP_SIZE, PAGE_SIZE};
 use crate::println;
 
-///将 buddy_system_allocator 中提供的 LockedHeap 实例化成一个全局变量，
+//堆中剩余的空间少于这么多字节时就提前扩展堆。扩展堆的时候物理页帧分配器自己也要在堆上分配内存，
+//所以必须给它留出余量
+const HEAP_LOW_WATERMARK: usize = 64 * 1024;
+//每次扩展堆时至少向物理页帧分配器申请这么多个物理页帧
+const HEAP_GROW_PAGES: usize = 64;
+
+//以 .bss 中的 HEAP_SPACE 作为初始的堆，空间不足时从物理页帧分配器中取出物理页帧加入堆中
+pub struct GrowableHeap {
+    heap: LockedHeap,
+    //正在扩展堆，这期间物理页帧分配器内部的分配只能使用堆中已有的空间
+    growing: AtomicBool,
+}
+
+impl GrowableHeap {
+    //向物理页帧分配器申请连续的物理页帧加入堆中，至少能满足 size 字节的分配。内核地址空间恒等映射了全部物理内存，
+    //所以物理页帧的物理地址可以直接作为堆中的地址使用。加入堆中的物理页帧不会再被归还
+    fn grow(&self, size: usize) -> bool {
+        if self.growing.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        //伙伴分配器需要按自身大小对齐的空闲块，申请两倍的大小才能保证其中有这样的块
+        let pages = (2 * size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let ppn = heap_frames_alloc(pages.max(HEAP_GROW_PAGES))
+            .map(|ppn| (ppn, pages.max(HEAP_GROW_PAGES)))
+            .or_else(|| heap_frames_alloc(pages.max(1)).map(|ppn| (ppn, pages.max(1))));
+        if let Some((ppn, pages)) = ppn {
+            let start = PhysAddr::from(ppn).0;
+            unsafe {
+                self.heap.lock().add_to_heap(start, start + pages * PAGE_SIZE);
+            }
+        }
+        self.growing.store(false, Ordering::Relaxed);
+        ppn.is_some()
+    }
+    //堆的总字节数和已经分配出去的字节数
+    fn stats(&self) -> (usize, usize) {
+        let heap = self.heap.lock();
+        (heap.stats_total_bytes(), heap.stats_alloc_actual())
+    }
+}
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.heap.lock().alloc(layout).ok();
+        let ptr = match ptr {
+            Some(ptr) => ptr,
+            None => {
+                if !self.grow(layout.size().max(layout.align())) {
+                    return core::ptr::null_mut();
+                }
+                match self.heap.lock().alloc(layout) {
+                    Ok(ptr) => ptr,
+                    Err(_) => return core::ptr::null_mut(),
+                }
+            }
+        };
+        let (total, used) = self.stats();
+        if total - used < HEAP_LOW_WATERMARK {
+            self.grow(0);
+        }
+        ptr.as_ptr()
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
+
+///将可以扩展的 GrowableHeap 实例化成一个全局变量，
 ///并使用 alloc 要求的 #[global_allocator] 语义项进行标记。
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: GrowableHeap = GrowableHeap {
+    heap: LockedHeap::empty(),
+    growing: AtomicBool::new(false),
+};
 
 /// Alloc error handler
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
-    panic!("Heap allocation error, layout = {:?}", layout);
+    let (total, used) = HEAP_ALLOCATOR.stats();
+    panic!(
+        "Heap allocation error, layout = {:?}, heap used {} / {} bytes",
+        layout, used, total
+    );
 }
 
-//声明一块 static mut 且被零初始化的字节数组的内存，位于内核的 .bss 段中
+//声明一块 static mut 且被零初始化的字节数组的内存，位于内核的 .bss 段中，作为初始的堆
 static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
 
 pub fn init_heap() {
     unsafe {
         HEAP_ALLOCATOR
+            .heap
             .lock()
             .init(HEAP_SPACE.as_ptr() as usize, KERNEL_HEAP_SIZE);
     }
 }
 
+//内核堆的总字节数和已经分配出去的字节数
+pub fn heap_stats() -> (usize, usize) {
+    HEAP_ALLOCATOR.stats()
+}
+
 //使用智能指针 Box<T> 和向量 Vec<T> 在堆上分配数据并管理它们，
 //通过 as_ref 和 as_ptr 方法可以分别看到它们指向的数据的位置，
 //能够确认它们的确在位于 .bss 段的堆上
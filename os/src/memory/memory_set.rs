@@ -2,8 +2,11 @@ use super::{frame_alloc, FrameTracker};
 use super::{PTEFlags, PageTable, PageTableEntry};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
-use crate::sync::UPSafeCell;
+use super::shm::SharedMemory;
+use super::swap::{swap_alloc, swap_dup, swap_exclusive, swap_free, swap_read, swap_write};
+use crate::config::{MEMORY_END, MMAP_BASE, MMIO, PAGE_SIZE, TRAMPOLINE, USER_SPACE_TOP};
+use crate::fs::{File, PageCache};
+use crate::sync::{futex_page_waited, UPSafeCell};
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use alloc::sync::Arc;
@@ -36,9 +39,36 @@ lazy_static! {
 pub struct MapArea {
     //一段虚拟页号的连续区间，表示该逻辑段在地址区间中的位置和长度
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    //fork 之后父子进程的同一个页面共享同一个物理页帧，因此用 Arc 对物理页帧进行引用计数
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
+    //页面在交换文件中的副本所在的槽位。不在 data_frames 中的页面如果在这里有槽位，说明它已经被换出；
+    //在内存中的页面也可能保留着换入之前的槽位，只要之后没有被写过就不必再次写入交换文件
+    swap_slots: BTreeMap<VirtPageNum, usize>,
     map_type: MapType,
     map_perm: MapPermission,
+    //文件映射的逻辑段对应的文件，匿名的逻辑段为 None
+    file: Option<FileMapping>,
+    //映射了共享内存段的逻辑段对应的共享内存段
+    shm: Option<ShmMapping>,
+}
+
+//文件映射的逻辑段对应的文件，逻辑段的第一个页面对应文件中 offset 处的页面
+#[derive(Clone)]
+pub struct FileMapping {
+    //映射期间文件一直保持打开状态
+    pub file: Arc<dyn File + Send + Sync>,
+    pub cache: Arc<PageCache>,
+    pub offset: usize,
+    //共享映射直接使用页缓存中的页面，用户的修改最终会写回文件；私有映射在写入时复制一份自己的页面
+    pub shared: bool,
+}
+
+//共享内存段的映射
+#[derive(Clone)]
+struct ShmMapping {
+    segment: Arc<SharedMemory>,
+    //以 SHM_RDONLY 方式映射时为 false ，之后也不能通过 mprotect 改为可写
+    writable: bool,
 }
 
 // 描述该逻辑段内的所有虚拟页面映射到物理页帧的同一种方式，
@@ -79,6 +109,11 @@ pub struct MemorySet {
     //两部分合在一起构成了一个地址空间所需的所有物理页帧
     page_table: PageTable,//该地址空间的多级页表，挂着所有多级页表的节点所在的物理页帧
     areas: Vec<MapArea>,//挂着对应逻辑段中的数据所在的物理页帧
+    //堆从最后一个 ELF 段之后的页边界 heap_bottom 开始，向上增长到程序断点 brk
+    heap_bottom: usize,
+    brk: usize,
+    //Clock 置换算法的指针，记录上一次换出的页面
+    clock_hand: VirtPageNum,
 }
 
 impl MapArea {
@@ -95,8 +130,11 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(start_vpn, end_vpn),
             data_frames: BTreeMap::new(),
+            swap_slots: BTreeMap::new(),
             map_type,
             map_perm,
+            file: None,
+            shm: None,
         }
     }
 
@@ -106,8 +144,80 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
             data_frames: BTreeMap::new(),
+            swap_slots: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            file: another.file.clone(),
+            shm: another.shm.clone(),
+        }
+    }
+
+    //是否是共享的文件映射
+    fn is_shared_file(&self) -> bool {
+        self.file.as_ref().map_or(false, |file| file.shared)
+    }
+
+    //逻辑段中的页面是否与其它地址空间共享：共享文件映射和共享内存段的页面被写入时不会复制
+    fn is_shared(&self) -> bool {
+        self.is_shared_file() || self.shm.is_some()
+    }
+
+    //文件映射中 vpn 处的页面对应文件的第几个页面
+    fn file_page(&self, vpn: VirtPageNum) -> usize {
+        self.file.as_ref().unwrap().offset / PAGE_SIZE + vpn.0 - self.vpn_range.get_start().0
+    }
+
+    //将逻辑段从 at 处分成两段，自身保留 at 之前的部分，返回 at 及之后的部分
+    fn split_off(&mut self, at: VirtPageNum) -> MapArea {
+        let start = self.vpn_range.get_start();
+        let tail = MapArea {
+            vpn_range: VPNRange::new(at, self.vpn_range.get_end()),
+            data_frames: self.data_frames.split_off(&at),
+            swap_slots: self.swap_slots.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            file: self.file.clone().map(|mut file| {
+                file.offset += (at.0 - start.0) * PAGE_SIZE;
+                file
+            }),
+            shm: self.shm.clone(),
+        };
+        self.vpn_range = VPNRange::new(start, at);
+        tail
+    }
+
+    //访问权限改变之后，重写逻辑段中已经在内存中的页面的页表项。写时复制的页面和以只读方式共享页缓存的页面
+    //仍然不能直接写入，A/D 标志位保持不变
+    fn update_flags(&self, page_table: &mut PageTable) {
+        let shared = self.is_shared();
+        for (vpn, frame) in self.data_frames.iter() {
+            let pte = page_table.translate(*vpn).unwrap();
+            let mut flags = PTEFlags::from_bits(self.map_perm.bits).unwrap()
+                | (pte.flags() & (PTEFlags::A | PTEFlags::D));
+            if Arc::strong_count(frame) > 1 && !shared {
+                flags.remove(PTEFlags::W);
+            }
+            page_table.remap(*vpn, pte.ppn(), flags);
+            //返回用户态时切换 satp 会刷新整个 TLB ，这里仍然刷新这个页面的表项，使修改立即生效
+            let va: VirtAddr = (*vpn).into();
+            unsafe {
+                asm!("sfence.vma {0}, zero", in(reg) usize::from(va));
+            }
+        }
+    }
+
+    //将共享文件映射中 [start_vpn, end_vpn) 内被用户写过（D 标志位为 1）的页面写回文件
+    fn sync(&self, page_table: &mut PageTable, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let file = match &self.file {
+            Some(file) if file.shared => file,
+            _ => return,
+        };
+        for vpn in self.data_frames.range(start_vpn..end_vpn).map(|(vpn, _)| *vpn) {
+            let pte = page_table.translate(vpn).unwrap();
+            if pte.flags().contains(PTEFlags::D) {
+                file.cache.write_back(self.file_page(vpn));
+                page_table.remap(vpn, pte.ppn(), pte.flags() - PTEFlags::D);
+            }
         }
     }
 
@@ -127,7 +237,7 @@ impl MapArea {
             MapType::Framed => {
                 let frame = frame_alloc().unwrap();
                 ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                self.data_frames.insert(vpn, Arc::new(frame));
             }
         }
         //页表项的标志位来源于当前逻辑段的类型为 MapPermission 的
@@ -142,7 +252,13 @@ impl MapArea {
         if self.map_type == MapType::Framed {
             //将虚拟页面被映射到的物理页帧FrameTracker从data_frames
             //中移除，这样这个物理页帧才能立即被回收以备后续分配
-            self.data_frames.remove(&vpn);
+            if let Some(slot) = self.swap_slots.remove(&vpn) {
+                swap_free(slot);
+            }
+            //按需分配的页面如果还没有被访问过或者已经被换出，页表中也就没有它的页表项
+            if self.data_frames.remove(&vpn).is_none() {
+                return;
+            }
         }
         page_table.unmap(vpn);//删除以传入的虚拟页号为键的键值对
     }
@@ -189,12 +305,31 @@ impl MapArea {
     }
 }
 
+//地址空间被回收（如 exec 替换掉原来的地址空间）的时候，共享文件映射中被写过的页面要写回文件
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        self.sync_all();
+    }
+}
+
+//逻辑段被回收的时候，释放它在交换文件中占用的槽位
+impl Drop for MapArea {
+    fn drop(&mut self) {
+        for slot in self.swap_slots.values() {
+            swap_free(*slot);
+        }
+    }
+}
+
 impl MemorySet {
     //新建一个空的地址空间
     pub fn new_bare() -> Self {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            heap_bottom: 0,
+            brk: 0,
+            clock_hand: VirtPageNum(0),
         }
     }
 
@@ -213,6 +348,23 @@ impl MemorySet {
         self.areas.push(map_area);
     }
 
+    //插入一个按需分配物理页帧的 Framed 逻辑段：只有初始数据 data 覆盖到的页面会立即映射并写入数据，
+    //其余页面等到第一次被访问触发页错误的时候再由 handle_page_fault 分配
+    fn push_lazy(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
+        assert_eq!(map_area.map_type, MapType::Framed);
+        if let Some(data) = data {
+            let start_vpn = map_area.vpn_range.get_start();
+            let data_end_vpn = VirtPageNum(start_vpn.0 + (data.len() + PAGE_SIZE - 1) / PAGE_SIZE);
+            for vpn in VPNRange::new(start_vpn, data_end_vpn) {
+                map_area.map_one(&mut self.page_table, vpn);
+            }
+            if !data.is_empty() {
+                map_area.copy_data(&mut self.page_table, data);
+            }
+        }
+        self.areas.push(map_area);
+    }
+
     /// Mention that trampoline is not collected by areas.
     //直接在多级页表中插入一个从地址空间的最高虚拟页面映射到跳板汇编代码
     //所在的物理页帧的键值对，访问权限与代码段相同，即RX
@@ -253,6 +405,175 @@ impl MemorySet {
         );
     }
 
+    //映射一段匿名内存，start 为 0 时由内核从 MMAP_BASE 开始寻找足够大的空闲区域，否则 start 必须按页对齐
+    //且不能与已有的逻辑段重叠。成功时返回映射的起始地址
+    pub fn mmap(
+        &mut self,
+        start: usize,
+        len: usize,
+        permission: MapPermission,
+        file: Option<FileMapping>,
+    ) -> Option<usize> {
+        let (start_va, end_va) = self.find_free_range(start, len)?;
+        let mut area = MapArea::new(start_va, end_va, MapType::Framed, permission | MapPermission::U);
+        area.file = file;
+        self.push_lazy(area, None);
+        Some(start_va.into())
+    }
+
+    //为长度为 len 的映射寻找位置。start 为 0 时从 MMAP_BASE 开始寻找足够大的空闲区域，否则 start 必须按页对齐
+    //且 [start, start + len) 不能与已有的逻辑段重叠。返回按页对齐的起止地址
+    fn find_free_range(&self, start: usize, len: usize) -> Option<(VirtAddr, VirtAddr)> {
+        if len == 0 || len > USER_SPACE_TOP || start % PAGE_SIZE != 0 || start >= USER_SPACE_TOP {
+            return None;
+        }
+        let page_count = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let top_vpn = VirtAddr::from(USER_SPACE_TOP).floor();
+        let mut start_vpn = if start == 0 {
+            VirtAddr::from(MMAP_BASE).floor()
+        } else {
+            VirtAddr::from(start).floor()
+        };
+        loop {
+            let end_vpn = VirtPageNum(start_vpn.0 + page_count);
+            if end_vpn > top_vpn {
+                return None;
+            }
+            //跳过挡在前面的逻辑段继续向高地址寻找
+            match self.areas.iter().find(|area| {
+                area.vpn_range.get_start() < end_vpn && start_vpn < area.vpn_range.get_end()
+            }) {
+                Some(_) if start != 0 => return None,
+                Some(area) => start_vpn = area.vpn_range.get_end(),
+                None => return Some((start_vpn.into(), end_vpn.into())),
+            }
+        }
+    }
+
+    //将共享内存段映射到地址空间中，start 的含义与 mmap 相同。共享内存段的物理页帧已经分配好，直接映射全部页面。
+    //成功时返回映射的起始地址
+    pub fn attach_shm(
+        &mut self,
+        start: usize,
+        segment: Arc<SharedMemory>,
+        permission: MapPermission,
+    ) -> Option<usize> {
+        let (start_va, end_va) = self.find_free_range(start, segment.len())?;
+        let mut area = MapArea::new(start_va, end_va, MapType::Framed, permission | MapPermission::U);
+        let pte_flags = PTEFlags::from_bits(area.map_perm.bits).unwrap();
+        for (vpn, frame) in area.vpn_range.into_iter().zip(segment.frames.iter()) {
+            self.page_table.map(vpn, frame.ppn, pte_flags);
+            area.data_frames.insert(vpn, frame.clone());
+        }
+        area.shm = Some(ShmMapping {
+            segment,
+            writable: permission.contains(MapPermission::W),
+        });
+        self.areas.push(area);
+        Some(start_va.into())
+    }
+
+    //解除 attach_shm 在 start 处建立的共享内存段映射
+    pub fn detach_shm(&mut self, start: usize) -> bool {
+        let len = match self.areas.iter().find(|area| {
+            area.shm.is_some() && usize::from(VirtAddr::from(area.vpn_range.get_start())) == start
+        }) {
+            Some(area) => area.shm.as_ref().unwrap().segment.len(),
+            None => return false,
+        };
+        self.munmap(start, len)
+    }
+
+    //解除 [start, start + len) 的映射，start 必须按页对齐。区间可以覆盖多个逻辑段，也可以只覆盖某个
+    //逻辑段的一部分：逻辑段被截短，或者从中间被挖掉一块而分裂成两段。区间内没有映射的页面会被忽略
+    pub fn munmap(&mut self, start: usize, len: usize) -> bool {
+        if len == 0 || start % PAGE_SIZE != 0 || start >= USER_SPACE_TOP {
+            return false;
+        }
+        let len = len.min(USER_SPACE_TOP - start);
+        self.unmap_range(
+            VirtAddr::from(start).floor(),
+            VirtAddr::from(start + len).ceil(),
+        );
+        true
+    }
+
+    //解除 vpn 这一个页面的映射，它所在逻辑段中的其它页面不受影响。线程的 Trap 上下文在 USER_SPACE_TOP 之上，
+    //各个线程的 Trap 上下文页面又彼此相邻，线程退出时用它来只回收自己的那一页
+    pub fn unmap_page(&mut self, vpn: VirtPageNum) {
+        self.unmap_range(vpn, VirtPageNum(vpn.0 + 1));
+    }
+
+    fn unmap_range(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let mut new_areas = Vec::new();
+        for area in self.areas.iter_mut() {
+            let area_start = area.vpn_range.get_start();
+            let area_end = area.vpn_range.get_end();
+            if area_end <= start_vpn || end_vpn <= area_start {
+                continue;
+            }
+            let unmap_start = area_start.max(start_vpn);
+            let unmap_end = area_end.min(end_vpn);
+            area.sync(&mut self.page_table, unmap_start, unmap_end);
+            for vpn in VPNRange::new(unmap_start, unmap_end) {
+                area.unmap_one(&mut self.page_table, vpn);
+            }
+            //被挖掉的部分之后还有剩余的页面，将它们分出去成为一个新的逻辑段
+            if unmap_end < area_end {
+                new_areas.push(area.split_off(unmap_end));
+            }
+            area.vpn_range = VPNRange::new(area_start, unmap_start);
+        }
+        //去掉被完全解除映射的逻辑段
+        self.areas
+            .retain(|area| area.vpn_range.get_start() < area.vpn_range.get_end());
+        self.areas.extend(new_areas);
+    }
+
+    //将程序断点移动到 new_brk ，堆中的页面随之被映射或解除映射。new_brk 低于堆底、或者堆需要增长的部分与
+    //其它逻辑段重叠时不做修改。返回修改之后的程序断点，new_brk 为 0 时即返回当前的程序断点
+    pub fn set_brk(&mut self, new_brk: usize) -> usize {
+        if new_brk < self.heap_bottom || new_brk >= USER_SPACE_TOP {
+            return self.brk;
+        }
+        let heap_bottom_vpn = VirtAddr::from(self.heap_bottom).floor();
+        let old_end_vpn = VirtAddr::from(self.brk).ceil();
+        let new_end_vpn = VirtAddr::from(new_brk).ceil();
+        if new_end_vpn > old_end_vpn {
+            if self.areas.iter().any(|area| {
+                area.vpn_range.get_start() < new_end_vpn
+                    && old_end_vpn < area.vpn_range.get_end()
+            }) {
+                return self.brk;
+            }
+            //优先扩展堆中最后一个逻辑段，它可能因为用户对堆中页面的 munmap 而不存在
+            if let Some(area) = self.areas.iter_mut().find(|area| {
+                area.vpn_range.get_end() == old_end_vpn
+                    && area.vpn_range.get_start() >= heap_bottom_vpn
+                    && area.file.is_none()
+                    && area.shm.is_none()
+            }) {
+                area.vpn_range = VPNRange::new(area.vpn_range.get_start(), new_end_vpn);
+            } else {
+                self.push_lazy(
+                    MapArea::new(
+                        old_end_vpn.into(),
+                        new_end_vpn.into(),
+                        MapType::Framed,
+                        MapPermission::R | MapPermission::W | MapPermission::U,
+                    ),
+                    None,
+                );
+            }
+        } else if new_end_vpn < old_end_vpn {
+            let start: VirtAddr = new_end_vpn.into();
+            let end: VirtAddr = old_end_vpn.into();
+            self.munmap(start.into(), usize::from(end) - usize::from(start));
+        }
+        self.brk = new_brk;
+        self.brk
+    }
+
     /// Without kernel stacks.
     //生成内核的地址空间,映射跳板和地址空间中最低256GB中的内核逻辑段
     pub fn new_kernel() -> Self {
@@ -336,8 +657,8 @@ impl MemorySet {
     //创建应用地址空间的时候，我们需要对get_app_data得到的ELF格式数据进行解析，
     //找到各个逻辑段所在位置和访问限制并插入进来，最终得到一个完整的应用地址空间
     /// Include sections in elf and trampoline,
-    /// also returns user_sp_base and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// also returns entry point.
+    pub fn from_elf(elf_data: &[u8]) -> (Self, usize) {
         let mut memory_set = Self::new_bare();
         //将跳板插入到应用地址空间
         memory_set.map_trampoline();
@@ -374,67 +695,399 @@ impl MemorySet {
                 //创建逻辑段
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
                 max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(//push 到应用地址空间
+                memory_set.push_lazy(//push 到应用地址空间，没有文件数据的页面（如 .bss）按需分配
                     map_area,
                     //需要完成数据拷贝，当前program header数据被存放的位置可以通过ph.offset()和ph.file_size()来找到
                     Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
                 );
             }
         }
-        //处理用户栈
+        //堆紧跟在最后一个 ELF 段之后，初始为空
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
-        // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set.push(
-            MapArea::new(
-                user_stack_bottom.into(),
-                user_stack_top.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
-            None,
-        );
-        // map TrapContext
-        memory_set.push(
-            MapArea::new(
-                TRAP_CONTEXT.into(),
-                TRAMPOLINE.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W,
-            ),
-            None,
-        );
-        (
-            memory_set,
-            user_stack_top,
-            elf.header.pt2.entry_point() as usize,
-        )
+        memory_set.heap_bottom = max_end_va.into();
+        memory_set.brk = memory_set.heap_bottom;
+        //用户栈和 Trap 上下文属于线程，由创建线程的时候分别映射
+        (memory_set, elf.header.pt2.entry_point() as usize)
     }
 
-    //复制一个完全相同的地址空间
-    pub fn from_existed_user(user_space: &MemorySet) -> MemorySet {
+    //复制一个完全相同的地址空间。除了线程的 Trap 上下文之外，子进程的页面并不立即复制，而是与父进程共享同一个
+    //物理页帧并在父子双方的页表中都去掉写权限，等到某一方写入触发页错误的时候再由 handle_page_fault 复制
+    pub fn from_existed_user(user_space: &mut MemorySet) -> MemorySet {
         //通过 new_bare 新创建一个空的地址空间
         let mut memory_set = Self::new_bare();
         //通过map_trampoline为这个地址空间映射上跳板页面，这是因为我们解析ELF创建地址空间的时候，
         //并没有将跳板页作为一个单独的逻辑段插入到地址空间的逻辑段向量areas中
         memory_set.map_trampoline();
-        //遍历原地址空间中的所有逻辑段，将复制之后的逻辑段插入新的地址空间，在插入的时候就已经实际分配了物理页帧了
+        memory_set.heap_bottom = user_space.heap_bottom;
+        memory_set.brk = user_space.brk;
         for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
-            //遍历逻辑段中的每个虚拟页面，对应完成数据复制，这只需要找出两个地址空间中的虚拟页面各被映射到哪个物理页帧，
-            //就可转化为将数据从物理内存中的一个位置复制到另一个位置，使用copy_from_slice即可轻松实现
-            for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
-                let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
-                dst_ppn
+            //内核直接通过物理地址读写 Trap 上下文，不会经过写时复制的检查，所以它仍需要立即复制。
+            //用户地址空间中只有 Trap 上下文所在的逻辑段是用户不能访问的
+            if !area.map_perm.contains(MapPermission::U) {
+                memory_set.push(MapArea::from_another(area), None);
+                for vpn in area.vpn_range {
+                    let src_ppn = user_space.translate(vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
+                    dst_ppn
+                        .get_bytes_array()
+                        .copy_from_slice(src_ppn.get_bytes_array());
+                }
+                continue;
+            }
+            let mut new_area = MapArea::from_another(area);
+            let mut pte_flags = PTEFlags::from_bits(area.map_perm.bits).unwrap();
+            //共享文件映射和共享内存段的页面本来就由父子进程共享，不需要写时复制
+            let shared = area.is_shared();
+            if !shared {
+                pte_flags.remove(PTEFlags::W);
+            }
+            for (vpn, frame) in area.data_frames.iter() {
+                memory_set.page_table.map(*vpn, frame.ppn, pte_flags);
+                if !shared {
+                    user_space.page_table.remap(*vpn, frame.ppn, pte_flags);
+                }
+                new_area.data_frames.insert(*vpn, frame.clone());
+            }
+            //已经被换出的页面由父子进程共同引用交换文件中的同一个槽位
+            for (vpn, slot) in area.swap_slots.iter() {
+                if !area.data_frames.contains_key(vpn) {
+                    swap_dup(*slot);
+                    new_area.swap_slots.insert(*vpn, *slot);
+                }
+            }
+            memory_set.areas.push(new_area);
+        }
+        memory_set
+    }
+
+    //处理用户对 vpn 的访问引起的页错误，access 是这次访问需要的权限 R/W/X ，为空时表示只需要页面在内存中。
+    //页面属于允许这种访问的 Framed 逻辑段时：不在内存中的页面从交换文件中读回，文件映射的页面使用页缓存中的页面，
+    //其余页面分配一个全零的物理页帧；
+    //写入写时复制的页面时，物理页帧仍与其它地址空间共享就复制一份新的物理页帧换上，否则直接恢复写权限。
+    //同时设置页表项的 A/D 标志位，以免在不会自动设置它们的硬件上反复触发页错误。返回 false 说明这是一次非法访问
+    //或者物理内存和交换文件都已经用尽
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: MapPermission) -> bool {
+        let idx = match self.areas.iter().position(|area| {
+            area.vpn_range.get_start() <= vpn && vpn < area.vpn_range.get_end()
+        }) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let area = &self.areas[idx];
+        //只处理用户可以访问的逻辑段，Trap 上下文所在的逻辑段不在此列
+        if area.map_type != MapType::Framed || !area.map_perm.contains(access | MapPermission::U) {
+            return false;
+        }
+        let write = access.contains(MapPermission::W);
+        let mut pte_flags = PTEFlags::from_bits(area.map_perm.bits).unwrap() | PTEFlags::A;
+        if write {
+            pte_flags |= PTEFlags::D;
+        }
+        if let Some(frame) = area.data_frames.get(&vpn) {
+            let src_ppn = frame.ppn;
+            let shared = Arc::strong_count(frame) > 1 && !area.is_shared();
+            let pte = self.page_table.translate(vpn).unwrap();
+            if !write || pte.writable() {
+                let mut flags = pte.flags() | PTEFlags::A;
+                if write {
+                    flags |= PTEFlags::D;
+                }
+                self.page_table.remap(vpn, pte.ppn(), flags);
+                return true;
+            }
+            let ppn = if shared {
+                let new_frame = match self.alloc_user_frame() {
+                    Some(frame) => frame,
+                    None => return false,
+                };
+                new_frame
+                    .ppn
                     .get_bytes_array()
                     .copy_from_slice(src_ppn.get_bytes_array());
+                let ppn = new_frame.ppn;
+                self.areas[idx].data_frames.insert(vpn, Arc::new(new_frame));
+                ppn
+            } else {
+                src_ppn
+            };
+            self.page_table.remap(vpn, ppn, pte_flags);
+            return true;
+        }
+        if !area.swap_slots.contains_key(&vpn) {
+            if let Some(file) = area.file.clone() {
+                return self.map_file_page(idx, vpn, file, pte_flags);
+            }
+        }
+        let frame = match self.alloc_user_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        let area = &mut self.areas[idx];
+        if let Some(slot) = area.swap_slots.get(&vpn).copied() {
+            swap_read(slot, frame.ppn);
+            //马上就会被写入的页面不必保留交换文件中的副本
+            if write {
+                area.swap_slots.remove(&vpn);
+                swap_free(slot);
+            }
+        }
+        self.page_table.map(vpn, frame.ppn, pte_flags);
+        area.data_frames.insert(vpn, Arc::new(frame));
+        true
+    }
+
+    //将第 idx 个逻辑段中 vpn 处的文件映射页面映射到页缓存中对应的页面。共享映射直接使用页缓存中的页面；
+    //私有映射在写入时复制一份，只是读取时则以只读方式共享页缓存中的页面，之后写入时再复制
+    fn map_file_page(
+        &mut self,
+        idx: usize,
+        vpn: VirtPageNum,
+        file: FileMapping,
+        pte_flags: PTEFlags,
+    ) -> bool {
+        let index = self.areas[idx].file_page(vpn);
+        let page = match file.cache.get(index) {
+            Some(page) => page,
+            None => match self.alloc_user_frame() {
+                Some(frame) => file.cache.fill(index, frame),
+                None => return false,
+            },
+        };
+        if file.shared {
+            self.page_table.map(vpn, page.ppn, pte_flags);
+            self.areas[idx].data_frames.insert(vpn, page);
+        } else if pte_flags.contains(PTEFlags::D) {
+            let frame = match self.alloc_user_frame() {
+                Some(frame) => frame,
+                None => return false,
+            };
+            frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(page.ppn.get_bytes_array());
+            self.page_table.map(vpn, frame.ppn, pte_flags);
+            self.areas[idx].data_frames.insert(vpn, Arc::new(frame));
+        } else {
+            self.page_table
+                .map(vpn, page.ppn, pte_flags - PTEFlags::W);
+            self.areas[idx].data_frames.insert(vpn, page);
+        }
+        true
+    }
+
+    //为用户页面分配一个物理页帧。frame_alloc 只会从其它进程中换出页面，本地址空间正在被使用而被跳过，
+    //所以其它进程中没有页面可以换出时再换出本地址空间中的一个页面并重试
+    fn alloc_user_frame(&mut self) -> Option<FrameTracker> {
+        loop {
+            if let Some(frame) = frame_alloc() {
+                return Some(frame);
+            }
+            if !self.swap_out_one() {
+                return None;
+            }
+        }
+    }
+
+    //Clock 置换算法：从上次换出的页面之后开始依次检查本地址空间中在内存里的用户页面，最近访问过的页面
+    //（A 标志位为 1）清除 A 标志位再给它一次机会，遇到没有访问过的页面就将它换出，因此转两圈之内一定能找到。
+    //与其它地址空间共享的写时复制页面以及有任务在上面等待 futex 的页面不会被换出
+    pub fn swap_out_one(&mut self) -> bool {
+        let mut candidates: Vec<VirtPageNum> = self
+            .areas
+            .iter()
+            .filter(|area| {
+                area.map_type == MapType::Framed
+                    && area.map_perm.contains(MapPermission::U)
+                    && !area.is_shared()
+            })
+            .flat_map(|area| {
+                area.data_frames
+                    .iter()
+                    .filter(|(_, frame)| {
+                        Arc::strong_count(frame) == 1 && !futex_page_waited(frame.ppn)
+                    })
+                    .map(|(vpn, _)| *vpn)
+            })
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+        candidates.sort();
+        let start = candidates
+            .iter()
+            .position(|vpn| *vpn > self.clock_hand)
+            .unwrap_or(0);
+        for i in 0..candidates.len() * 2 {
+            let vpn = candidates[(start + i) % candidates.len()];
+            let pte = self.page_table.translate(vpn).unwrap();
+            if pte.flags().contains(PTEFlags::A) {
+                self.page_table.remap(vpn, pte.ppn(), pte.flags() - PTEFlags::A);
+            } else {
+                self.clock_hand = vpn;
+                return self.swap_out(vpn);
+            }
+        }
+        false
+    }
+
+    //将 vpn 处的页面写入交换文件并释放它的物理页帧。如果页面在交换文件中已经有一份副本且之后没有被写过
+    //（D 标志位为 0），就不必再写一次。交换文件已满时返回 false
+    fn swap_out(&mut self, vpn: VirtPageNum) -> bool {
+        let pte = self.page_table.translate(vpn).unwrap();
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() <= vpn && vpn < area.vpn_range.get_end())
+            .unwrap();
+        let old_slot = area.swap_slots.get(&vpn).copied();
+        if pte.flags().contains(PTEFlags::D) || old_slot.is_none() {
+            //fork 之后仍被其它地址空间引用的槽位不能直接覆盖
+            let slot = match old_slot {
+                Some(slot) if swap_exclusive(slot) => slot,
+                _ => {
+                    let slot = match swap_alloc() {
+                        Some(slot) => slot,
+                        None => return false,
+                    };
+                    if let Some(old_slot) = old_slot {
+                        swap_free(old_slot);
+                    }
+                    slot
+                }
+            };
+            swap_write(slot, pte.ppn());
+            area.swap_slots.insert(vpn, slot);
+        }
+        area.data_frames.remove(&vpn);
+        self.page_table.unmap(vpn);
+        true
+    }
+
+    //内核通过物理地址写入用户空间 [start_va, start_va + len) 之前调用，提前分配其中尚未分配的页面并解除写时复制
+    //页面的共享，否则写入会绕过页表的写权限检查而修改到其它地址空间也能看到的物理页帧。
+    //区间中有用户自己也不能写入的页面时返回 false ，内核此时不能代替用户写入
+    pub fn prepare_user_write(&mut self, start_va: VirtAddr, len: usize) -> bool {
+        self.prepare_user_access(start_va, len, MapPermission::W)
+    }
+
+    //内核通过物理地址读取用户空间 [start_va, start_va + len) 之前调用，提前换入或分配其中不在内存中的页面。
+    //区间中有用户自己也不能读取的页面时返回 false
+    pub fn prepare_user_read(&mut self, start_va: VirtAddr, len: usize) -> bool {
+        self.prepare_user_access(start_va, len, MapPermission::R)
+    }
+
+    fn prepare_user_access(
+        &mut self,
+        start_va: VirtAddr,
+        len: usize,
+        access: MapPermission,
+    ) -> bool {
+        if len == 0 {
+            return true;
+        }
+        let end = match usize::from(start_va).checked_add(len) {
+            Some(end) if end <= USER_SPACE_TOP => end,
+            _ => return false,
+        };
+        let start_vpn = start_va.floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        VPNRange::new(start_vpn, end_vpn)
+            .into_iter()
+            .all(|vpn| self.handle_page_fault(vpn, access))
+    }
+
+    //将 [start, start + len) 中页面的访问权限修改为 permission ，start 必须按页对齐，区间中的每个页面都必须属于
+    //用户的逻辑段。区间的两端落在逻辑段中间时先将逻辑段分裂开，修改之后再合并访问权限相同的相邻逻辑段
+    pub fn mprotect(&mut self, start: usize, len: usize, permission: MapPermission) -> bool {
+        if len == 0 || start % PAGE_SIZE != 0 || start >= USER_SPACE_TOP {
+            return false;
+        }
+        let len = len.min(USER_SPACE_TOP - start);
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        let mut covered = 0;
+        for area in self.areas.iter() {
+            let area_start = area.vpn_range.get_start();
+            let area_end = area.vpn_range.get_end();
+            if area_end <= start_vpn || end_vpn <= area_start {
+                continue;
+            }
+            if !area.map_perm.contains(MapPermission::U) {
+                return false;
+            }
+            //以只读方式打开的文件不能通过共享映射被修改
+            if permission.contains(MapPermission::W)
+                && area
+                    .file
+                    .as_ref()
+                    .map_or(false, |file| file.shared && !file.file.writable())
+            {
+                return false;
+            }
+            //以只读方式映射的共享内存段不能改为可写
+            if permission.contains(MapPermission::W)
+                && area.shm.as_ref().map_or(false, |shm| !shm.writable)
+            {
+                return false;
+            }
+            covered += area_end.min(end_vpn).0 - area_start.max(start_vpn).0;
+        }
+        if covered != end_vpn.0 - start_vpn.0 {
+            return false;
+        }
+        self.split_area_at(start_vpn);
+        self.split_area_at(end_vpn);
+        for area in self.areas.iter_mut() {
+            if start_vpn <= area.vpn_range.get_start() && area.vpn_range.get_end() <= end_vpn {
+                area.map_perm = permission | MapPermission::U;
+                area.update_flags(&mut self.page_table);
+            }
+        }
+        self.merge_areas(start_vpn, end_vpn);
+        true
+    }
+
+    //vpn 落在某个逻辑段中间时，将这个逻辑段从 vpn 处分成两段
+    fn split_area_at(&mut self, vpn: VirtPageNum) {
+        if let Some(area) = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() < vpn && vpn < area.vpn_range.get_end())
+        {
+            let tail = area.split_off(vpn);
+            self.areas.push(tail);
+        }
+    }
+
+    //合并接缝落在 [start_vpn, end_vpn] 之内、映射方式和访问权限都相同的用户匿名逻辑段。
+    //内核使用的逻辑段（例如各个线程相邻的 Trap 上下文）不参与合并，它们要各自单独回收
+    fn merge_areas(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        self.areas.sort_by_key(|area| area.vpn_range.get_start());
+        let mut i = 1;
+        while i < self.areas.len() {
+            let prev = &self.areas[i - 1];
+            let next = &self.areas[i];
+            let seam = prev.vpn_range.get_end();
+            if seam == next.vpn_range.get_start()
+                && start_vpn <= seam
+                && seam <= end_vpn
+                && prev.map_perm.contains(MapPermission::U)
+                && prev.map_type == next.map_type
+                && prev.map_perm == next.map_perm
+                && prev.file.is_none()
+                && next.file.is_none()
+                && prev.shm.is_none()
+                && next.shm.is_none()
+            {
+                let mut next = self.areas.remove(i);
+                let prev = &mut self.areas[i - 1];
+                prev.vpn_range = VPNRange::new(prev.vpn_range.get_start(), next.vpn_range.get_end());
+                prev.data_frames.append(&mut next.data_frames);
+                prev.swap_slots.append(&mut next.swap_slots);
+            } else {
+                i += 1;
             }
         }
-        memory_set
     }
 
     //将无符号 64 位无符号整数写入当前CPU的satp CSR，从这一刻开始
@@ -451,13 +1104,55 @@ impl MemorySet {
         self.page_table.translate(vpn)
     }
 
+    //vpn 处的页面所在的物理页帧，页面不在内存中时返回 None
+    pub fn frame(&self, vpn: VirtPageNum) -> Option<Arc<FrameTracker>> {
+        self.areas
+            .iter()
+            .find(|area| area.vpn_range.get_start() <= vpn && vpn < area.vpn_range.get_end())
+            .and_then(|area| area.data_frames.get(&vpn).cloned())
+    }
+
     //将地址空间中的逻辑段列表 areas 清空（即执行 Vec 向量清空）
     //导致应用地址空间被回收（即进程的数据和代码对应的物理页帧都被回收），
     //但用来存放页表的那些物理页帧此时还不会被回收（会由父进程最后回收子进程剩余的占用资源）
     pub fn recycle_data_pages(&mut self) {
         //*self = Self::new_bare();
+        self.sync_all();
         self.areas.clear();
     }
+
+    //将 [start, start + len) 中共享文件映射被写过的页面写回文件，start 必须按页对齐
+    pub fn msync(&mut self, start: usize, len: usize) -> bool {
+        if start % PAGE_SIZE != 0 || start >= USER_SPACE_TOP {
+            return false;
+        }
+        let len = len.min(USER_SPACE_TOP - start);
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        for area in self.areas.iter() {
+            let area_start = area.vpn_range.get_start();
+            let area_end = area.vpn_range.get_end();
+            if area_start < end_vpn && start_vpn < area_end {
+                area.sync(
+                    &mut self.page_table,
+                    area_start.max(start_vpn),
+                    area_end.min(end_vpn),
+                );
+            }
+        }
+        true
+    }
+
+    //将所有共享文件映射中被写过的页面写回文件
+    fn sync_all(&mut self) {
+        for area in self.areas.iter() {
+            area.sync(
+                &mut self.page_table,
+                area.vpn_range.get_start(),
+                area.vpn_range.get_end(),
+            );
+        }
+    }
 }
 
 //通过手动查内核多级页表的方式验证代码段和只读数据段不允许被写入，
@@ -3,13 +3,18 @@ mod frame_allocator;
 mod address;
 mod page_table;
 mod memory_set;
+mod shm;
+mod swap;
 use crate::println;
 
 pub use address::{PhysAddr, StepByOne, PhysPageNum, VirtAddr, VirtPageNum};
 use address::{ VPNRange};
 
 pub use page_table::{
+	copy_from_user,
+	copy_to_user,
 	translated_byte_buffer,
+	translated_ref,
 	translated_refmut, 
 	translated_str, 
 	PageTableEntry,
@@ -20,15 +25,20 @@ pub use page_table::{
 
 pub use frame_allocator::{
 	frame_alloc,
+	frame_alloc_contiguous,
 	FrameTracker,
 	frame_dealloc,
+	frame_dealloc_contiguous,
+	frame_stats,
 };
+pub use heap_allocator::heap_stats;
 
 use page_table::{
 	PTEFlags,
 };
-pub use memory_set::{kernel_token, MapPermission, MemorySet, KERNEL_SPACE};
+pub use memory_set::{kernel_token, FileMapping, MapPermission, MemorySet, KERNEL_SPACE};
 pub use memory_set::remap_test;
+pub use shm::{shm_get, shm_remove, shm_segment};
 
 pub fn init() {
 	    heap_allocator::init_heap();
@@ -1,6 +1,8 @@
 use bitflags::*;
 use super::{frame_alloc, FrameTracker, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::task::pin_user_page;
 use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 
@@ -151,6 +153,12 @@ impl PageTable {
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
+    //修改一个已经映射的虚拟页面的物理页号和标志位，用于写时复制时换上新的物理页帧或恢复写权限
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
 
     //提供一种类似 MMU 操作的手动查页表的方法
     /// Temporarily used to get arguments from user space.
@@ -185,16 +193,42 @@ impl PageTable {
     }
 }
 
+//手动查用户页表得到 vpn 所在的物理页帧。按需分配的页面可能还没有被用户访问过，已经被换出的页面也要先换入，
+//这时先让当前任务为它分配物理页帧。同时返回页帧的引用，调用者持有它期间页帧不会被换出或释放
+fn translate_user_page(
+    page_table: &PageTable,
+    token: usize,
+    vpn: VirtPageNum,
+) -> (PhysPageNum, Option<Arc<FrameTracker>>) {
+    let frame = pin_user_page(token, vpn);
+    let ppn = page_table
+        .translate(vpn)
+        .filter(|pte| pte.is_valid())
+        .unwrap()
+        .ppn();
+    (ppn, frame)
+}
+
+fn translate_user_va(page_table: &PageTable, token: usize, va: VirtAddr) -> PhysAddr {
+    let pa: PhysAddr = translate_user_page(page_table, token, va.floor()).0.into();
+    (usize::from(pa) + va.page_offset()).into()
+}
+
 /// translate a pointer to a mutable u8 Vec through page table
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+//返回的 UserBuffer 持有缓冲区所在的物理页帧，在它被丢弃之前这些页帧不会被换出，
+//即使访问缓冲区的任务在中途阻塞、其它任务换出页面或者进程退出也是如此
+pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> UserBuffer {
     let page_table = PageTable::from_token(token);
     let mut start = ptr as usize;
     let end = start + len;
     let mut v = Vec::new();
+    let mut frames = Vec::new();
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.floor();
-        let ppn = page_table.translate(vpn).unwrap().ppn();
+        //先固定已经翻译的页面，之后翻译其它页面时分配物理页帧引起的换出不会选中它们
+        let (ppn, frame) = translate_user_page(&page_table, token, vpn);
+        frames.extend(frame);
         vpn.step();
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end));
@@ -205,7 +239,7 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
         }
         start = end_va.into();
     }
-    v
+    UserBuffer { buffers: v, frames }
 }
 
 //从内核地址空间之外的某个应用的用户态地址空间中拿到一个字符串，其原理就是针对应用的字符串
@@ -215,10 +249,7 @@ pub fn translated_str(token: usize, ptr: *const u8) -> String {
     let mut string = String::new();
     let mut va = ptr as usize;
     loop {
-        let ch: u8 = *(page_table
-            .translate_va(VirtAddr::from(va))
-            .unwrap()
-            .get_mut());
+        let ch: u8 = *(translate_user_va(&page_table, token, VirtAddr::from(va)).get_mut());
         if ch == 0 {
             break;
         }
@@ -228,24 +259,54 @@ pub fn translated_str(token: usize, ptr: *const u8) -> String {
     string
 }
 
+///translate a generic through page table and return a reference
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+    let page_table = PageTable::from_token(token);
+    translate_user_va(&page_table, token, VirtAddr::from(ptr as usize)).get_mut()
+}
+
 ///translate a generic through page table and return a mutable reference
 pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
     let page_table = PageTable::from_token(token);
     let va = ptr as usize;
-    page_table
-        .translate_va(VirtAddr::from(va))
-        .unwrap()
-        .get_mut()
+    translate_user_va(&page_table, token, VirtAddr::from(va)).get_mut()
+}
+
+//将内核中的一个值按字节复制到用户地址空间 dst 处，dst 指向的区域可以跨越多个页面
+pub fn copy_to_user<T>(token: usize, dst: *mut T, src: &T) {
+    let src = unsafe {
+        core::slice::from_raw_parts(src as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    let mut start = 0;
+    let mut user_buf = translated_byte_buffer(token, dst as *const u8, src.len());
+    for buffer in user_buf.buffers.iter_mut() {
+        buffer.copy_from_slice(&src[start..start + buffer.len()]);
+        start += buffer.len();
+    }
+}
+
+//从用户地址空间 src 处按字节复制出一个值，src 指向的区域可以跨越多个页面
+pub fn copy_from_user<T: Copy>(token: usize, src: *const T) -> T {
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    let dst = unsafe {
+        core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, core::mem::size_of::<T>())
+    };
+    let mut start = 0;
+    let user_buf = translated_byte_buffer(token, src as *const u8, dst.len());
+    for buffer in user_buf.buffers.iter() {
+        dst[start..start + buffer.len()].copy_from_slice(buffer);
+        start += buffer.len();
+    }
+    unsafe { value.assume_init() }
 }
 
 pub struct UserBuffer {
     pub buffers: Vec<&'static mut [u8]>,
+    //缓冲区所在的物理页帧，持有它们的引用计数使页帧不会被换出
+    frames: Vec<Arc<FrameTracker>>,
 }
 
 impl UserBuffer {
-    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
-        Self { buffers }
-    }
     pub fn len(&self) -> usize {
         let mut total: usize = 0;
         for b in self.buffers.iter() {
@@ -261,6 +322,7 @@ impl IntoIterator for UserBuffer {
     fn into_iter(self) -> Self::IntoIter {
         UserBufferIterator {
             buffers: self.buffers,
+            _frames: self.frames,
             current_buffer: 0,
             current_idx: 0,
         }
@@ -269,6 +331,7 @@ impl IntoIterator for UserBuffer {
 
 pub struct UserBufferIterator {
     buffers: Vec<&'static mut [u8]>,
+    _frames: Vec<Arc<FrameTracker>>,
     current_buffer: usize,
     current_idx: usize,
 }
@@ -0,0 +1,95 @@
+//! System V 风格的共享内存段，同一组物理页帧可以被映射到多个进程的地址空间中
+use super::{frame_alloc, FrameTracker};
+use crate::config::{PAGE_SIZE, USER_SPACE_TOP};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+//不与任何键关联、总是新建共享内存段的键
+pub const IPC_PRIVATE: usize = 0;
+//shmget 的 flags
+pub const IPC_CREAT: usize = 0o1000;
+pub const IPC_EXCL: usize = 0o2000;
+
+//一个共享内存段。创建时就分配好全部物理页帧，被映射到的每个逻辑段都持有它的引用，
+//所以即使已经被 shmctl(IPC_RMID) 删除，物理页帧也要等到最后一个进程解除映射之后才会被回收
+pub struct SharedMemory {
+    key: usize,
+    pub frames: Vec<Arc<FrameTracker>>,
+}
+
+impl SharedMemory {
+    //共享内存段的大小
+    pub fn len(&self) -> usize {
+        self.frames.len() * PAGE_SIZE
+    }
+}
+
+pub struct ShmManager {
+    segments: BTreeMap<usize, Arc<SharedMemory>>,
+    next_id: usize,
+}
+
+impl ShmManager {
+    pub fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+    //key 已经有对应的共享内存段时返回它的编号，否则在 flags 包含 IPC_CREAT 时新建一个。
+    //已有的共享内存段比 size 小、或者 flags 同时包含 IPC_CREAT 和 IPC_EXCL 时失败
+    fn get(&mut self, key: usize, size: usize, flags: usize) -> Option<usize> {
+        if key != IPC_PRIVATE {
+            if let Some((id, segment)) = self
+                .segments
+                .iter()
+                .find(|(_, segment)| segment.key == key)
+            {
+                if flags & IPC_CREAT != 0 && flags & IPC_EXCL != 0 || size > segment.len() {
+                    return None;
+                }
+                return Some(*id);
+            }
+            if flags & IPC_CREAT == 0 {
+                return None;
+            }
+        }
+        //size 接近 usize::MAX 时向上取整会溢出。共享内存段还必须能够放进用户地址空间
+        let pages = size.checked_add(PAGE_SIZE - 1)? / PAGE_SIZE;
+        if pages == 0 || pages > USER_SPACE_TOP / PAGE_SIZE {
+            return None;
+        }
+        let mut frames = Vec::new();
+        for _ in 0..pages {
+            frames.push(Arc::new(frame_alloc()?));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.segments
+            .insert(id, Arc::new(SharedMemory { key, frames }));
+        Some(id)
+    }
+}
+
+lazy_static! {
+    pub static ref SHM_MANAGER: UPSafeCell<ShmManager> =
+        unsafe { UPSafeCell::new(ShmManager::new()) };
+}
+
+//获取 key 对应的共享内存段的编号，必要时新建一个
+pub fn shm_get(key: usize, size: usize, flags: usize) -> Option<usize> {
+    SHM_MANAGER.exclusive_access().get(key, size, flags)
+}
+
+//编号为 id 的共享内存段
+pub fn shm_segment(id: usize) -> Option<Arc<SharedMemory>> {
+    SHM_MANAGER.exclusive_access().segments.get(&id).cloned()
+}
+
+//删除编号为 id 的共享内存段，之后它不能再被 shmget 找到或者被 shmat 映射
+pub fn shm_remove(id: usize) -> bool {
+    SHM_MANAGER.exclusive_access().segments.remove(&id).is_some()
+}
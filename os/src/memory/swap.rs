@@ -0,0 +1,96 @@
+//! 将不常访问的用户页面换出到 easy-fs 上的交换文件
+use super::PhysPageNum;
+use crate::config::PAGE_SIZE;
+use crate::fs::open_swap_file;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use easy_fs::Inode;
+use lazy_static::*;
+
+//交换文件最多能容纳的页面数
+const SWAP_PAGES: usize = 2048;
+
+//交换文件被划分为一个个页面大小的槽位，以类似栈式物理页帧分配器的方式分配。
+//fork 之后父子进程可能引用同一个槽位，所以为每个槽位维护引用计数
+pub struct SwapManager {
+    //交换文件在根目录中的目录项对用户隐藏，所以它不会被用户删除或者改写
+    file: Option<Arc<Inode>>,
+    current: usize,
+    recycled: Vec<usize>,
+    ref_counts: Vec<usize>,
+}
+
+impl SwapManager {
+    pub fn new() -> Self {
+        Self {
+            file: None,
+            current: 0,
+            recycled: Vec::new(),
+            ref_counts: Vec::new(),
+        }
+    }
+    //第一次使用的时候才打开交换文件，此时文件系统一定已经可以使用了
+    fn inode(&mut self) -> Arc<Inode> {
+        self.file.get_or_insert_with(open_swap_file).clone()
+    }
+    fn alloc(&mut self) -> Option<usize> {
+        let slot = if let Some(slot) = self.recycled.pop() {
+            slot
+        } else if self.current < SWAP_PAGES {
+            self.current += 1;
+            self.ref_counts.push(0);
+            self.current - 1
+        } else {
+            return None;
+        };
+        self.ref_counts[slot] = 1;
+        Some(slot)
+    }
+    fn dup(&mut self, slot: usize) {
+        self.ref_counts[slot] += 1;
+    }
+    fn free(&mut self, slot: usize) {
+        assert!(self.ref_counts[slot] > 0, "swap slot {} has not been allocated!", slot);
+        self.ref_counts[slot] -= 1;
+        if self.ref_counts[slot] == 0 {
+            self.recycled.push(slot);
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref SWAP_MANAGER: UPSafeCell<SwapManager> =
+        unsafe { UPSafeCell::new(SwapManager::new()) };
+}
+
+//分配一个空闲的槽位，交换文件已满时返回 None
+pub fn swap_alloc() -> Option<usize> {
+    SWAP_MANAGER.exclusive_access().alloc()
+}
+
+//让另一个地址空间也引用这个槽位
+pub fn swap_dup(slot: usize) {
+    SWAP_MANAGER.exclusive_access().dup(slot);
+}
+
+pub fn swap_free(slot: usize) {
+    SWAP_MANAGER.exclusive_access().free(slot);
+}
+
+//槽位是否只被一个地址空间引用，只有这时才能直接覆盖其中的数据
+pub fn swap_exclusive(slot: usize) -> bool {
+    SWAP_MANAGER.exclusive_access().ref_counts[slot] == 1
+}
+
+//将物理页帧的内容写入槽位
+pub fn swap_write(slot: usize, ppn: PhysPageNum) {
+    let inode = SWAP_MANAGER.exclusive_access().inode();
+    assert_eq!(inode.write_at(slot * PAGE_SIZE, ppn.get_bytes_array()), PAGE_SIZE);
+}
+
+//将槽位中的内容读入物理页帧
+pub fn swap_read(slot: usize, ppn: PhysPageNum) {
+    let inode = SWAP_MANAGER.exclusive_access().inode();
+    assert_eq!(inode.read_at(slot * PAGE_SIZE, ppn.get_bytes_array()), PAGE_SIZE);
+}
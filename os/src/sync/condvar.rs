@@ -0,0 +1,30 @@
+//! Condition variables used by user programs
+use super::{Mutex, WaitQueue};
+use crate::task::sigkill_pending;
+use alloc::sync::Arc;
+
+//条件变量。等待的线程可能被信号提前唤醒，所以用户程序需要在循环中检查条件
+pub struct Condvar {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            wait_queue: WaitQueue::new(),
+        }
+    }
+    //唤醒一个等待的线程
+    pub fn signal(&self) {
+        self.wait_queue.wake_one();
+    }
+    //释放 mutex 并阻塞，被唤醒之后重新获取 mutex 。释放锁和阻塞之间不会有其它线程运行，所以不会错过 signal 。
+    //进程收到 SIGKILL 时返回 false
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> bool {
+        mutex.unlock();
+        if !sigkill_pending() {
+            self.wait_queue.wait();
+        }
+        mutex.lock()
+    }
+}
@@ -0,0 +1,69 @@
+//! Futex wait queues keyed by physical address
+use super::UPSafeCell;
+use crate::config::PAGE_SIZE;
+use crate::memory::{PhysAddr, PhysPageNum};
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use lazy_static::*;
+
+lazy_static! {
+    //按用户变量的物理地址区分的等待队列。共享内存、共享的文件映射以及同一进程中的不同线程
+    //看到的是同一个物理地址，所以它们可以在同一个 futex 上等待和唤醒。没有任务等待的地址不在表中
+    static ref FUTEX_QUEUES: UPSafeCell<BTreeMap<usize, VecDeque<Arc<TaskControlBlock>>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+//在物理地址 pa 处的 futex 上阻塞当前任务，直到被 futex_wake 唤醒。调用前必须释放当前任务和进程控制块的借用
+pub fn futex_wait(pa: PhysAddr) {
+    let task = current_task().unwrap();
+    let key: usize = pa.into();
+    FUTEX_QUEUES
+        .exclusive_access()
+        .entry(key)
+        .or_insert_with(VecDeque::new)
+        .push_back(task.clone());
+    block_current_and_run_next();
+    //被信号提前唤醒时自己还留在队列中，醒来后将自己移除，队列空了就删除这个地址
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    if let Some(queue) = queues.get_mut(&key) {
+        queue.retain(|t| !Arc::ptr_eq(t, &task));
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+}
+
+//唤醒最多 count 个在物理地址 pa 处等待的任务，返回实际唤醒的数量。
+//被信号唤醒但还没有运行的任务还留在队列中，跳过它们
+pub fn futex_wake(pa: PhysAddr, count: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let key: usize = pa.into();
+    let mut woken = 0;
+    if let Some(queue) = queues.get_mut(&key) {
+        while woken < count {
+            match queue.pop_front() {
+                Some(task) => {
+                    if wakeup_task(task) {
+                        woken += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+    woken
+}
+
+//物理页帧上是否有任务在等待 futex 。这样的页面不能被换出，否则它被换入时换了物理页帧，唤醒者就找不到等待者了
+pub fn futex_page_waited(ppn: PhysPageNum) -> bool {
+    let start: usize = PhysAddr::from(ppn).into();
+    FUTEX_QUEUES
+        .exclusive_access()
+        .range(start..start + PAGE_SIZE)
+        .next()
+        .is_some()
+}
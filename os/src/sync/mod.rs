@@ -1,4 +1,14 @@
 //! Synchronization and interior mutability primitives
+mod condvar;
+mod futex;
+mod mutex;
+mod semaphore;
 mod up;
+mod wait_queue;
 
+pub use condvar::Condvar;
+pub use futex::{futex_page_waited, futex_wait, futex_wake};
+pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use semaphore::Semaphore;
 pub use up::UPSafeCell;
+pub use wait_queue::WaitQueue;
@@ -0,0 +1,83 @@
+//! Mutexes used by user programs
+use super::{UPSafeCell, WaitQueue};
+use crate::task::{sigkill_pending, suspend_current_and_run_next};
+
+//用户程序使用的互斥锁。lock 在得到锁时返回 true ；等待期间普通的信号不会打断它，但进程收到 SIGKILL 时
+//放弃等待并返回 false ，让进程尽快退出。unlock 在锁没有被持有时返回 false
+pub trait Mutex: Sync + Send {
+    fn lock(&self) -> bool;
+    fn unlock(&self) -> bool;
+}
+
+//自旋锁：锁被占用时让出处理器，之后再重新尝试
+pub struct MutexSpin {
+    locked: UPSafeCell<bool>,
+}
+
+impl MutexSpin {
+    pub fn new() -> Self {
+        Self {
+            locked: unsafe { UPSafeCell::new(false) },
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) -> bool {
+        loop {
+            let mut locked = self.locked.exclusive_access();
+            if !*locked {
+                *locked = true;
+                return true;
+            }
+            drop(locked);
+            if sigkill_pending() {
+                return false;
+            }
+            suspend_current_and_run_next();
+        }
+    }
+    fn unlock(&self) -> bool {
+        core::mem::replace(&mut *self.locked.exclusive_access(), false)
+    }
+}
+
+//阻塞锁：锁被占用时阻塞在等待队列上，释放锁时唤醒一个等待的线程。被唤醒的线程重新竞争锁，
+//没有抢到的话继续等待
+pub struct MutexBlocking {
+    locked: UPSafeCell<bool>,
+    wait_queue: WaitQueue,
+}
+
+impl MutexBlocking {
+    pub fn new() -> Self {
+        Self {
+            locked: unsafe { UPSafeCell::new(false) },
+            wait_queue: WaitQueue::new(),
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) -> bool {
+        loop {
+            let mut locked = self.locked.exclusive_access();
+            if !*locked {
+                *locked = true;
+                return true;
+            }
+            drop(locked);
+            if sigkill_pending() {
+                return false;
+            }
+            self.wait_queue.wait();
+        }
+    }
+    fn unlock(&self) -> bool {
+        let was_locked = core::mem::replace(&mut *self.locked.exclusive_access(), false);
+        if was_locked {
+            self.wait_queue.wake_one();
+        }
+        was_locked
+    }
+}
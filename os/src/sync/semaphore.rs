@@ -0,0 +1,37 @@
+//! Counting semaphores used by user programs
+use super::{UPSafeCell, WaitQueue};
+use crate::task::sigkill_pending;
+
+//计数信号量。down 在计数为 0 时阻塞，直到其它线程 up ；与阻塞锁一样只有 SIGKILL 能让它放弃等待
+pub struct Semaphore {
+    count: UPSafeCell<usize>,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            count: unsafe { UPSafeCell::new(res_count) },
+            wait_queue: WaitQueue::new(),
+        }
+    }
+    pub fn up(&self) {
+        *self.count.exclusive_access() += 1;
+        self.wait_queue.wake_one();
+    }
+    //得到一个资源时返回 true ，进程收到 SIGKILL 时返回 false
+    pub fn down(&self) -> bool {
+        loop {
+            let mut count = self.count.exclusive_access();
+            if *count > 0 {
+                *count -= 1;
+                return true;
+            }
+            drop(count);
+            if sigkill_pending() {
+                return false;
+            }
+            self.wait_queue.wait();
+        }
+    }
+}
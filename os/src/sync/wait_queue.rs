@@ -0,0 +1,48 @@
+//! Queue of tasks blocked on an event
+use super::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+//在某个事件上阻塞的任务队列。内核在单核上运行且不会被中断打断，所以“检查条件后调用 wait”
+//这两步之间不会有其它任务插进来，也就不会丢失唤醒。任务被唤醒后可能仍不满足条件（例如被信号唤醒），
+//需要重新检查
+pub struct WaitQueue {
+    queue: UPSafeCell<VecDeque<Arc<TaskControlBlock>>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: unsafe { UPSafeCell::new(VecDeque::new()) },
+        }
+    }
+    //将当前任务加入队列并阻塞，直到被 wake_one 或 wake_all 唤醒。调用前必须释放当前任务控制块的借用
+    pub fn wait(&self) {
+        let task = current_task().unwrap();
+        self.queue.exclusive_access().push_back(task.clone());
+        block_current_and_run_next();
+        //被信号提前唤醒时自己还留在队列中，醒来后将自己移除，反复被打断的等待不会让队列越来越长
+        self.queue
+            .exclusive_access()
+            .retain(|t| !Arc::ptr_eq(t, &task));
+    }
+    //唤醒队头的一个任务，返回是否真的唤醒了任务。被信号唤醒但还没有运行的任务还留在队列中，跳过它们
+    pub fn wake_one(&self) -> bool {
+        loop {
+            let task = self.queue.exclusive_access().pop_front();
+            match task {
+                Some(task) => {
+                    if wakeup_task(task) {
+                        return true;
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+    //唤醒队列中的所有任务
+    pub fn wake_all(&self) {
+        while self.wake_one() {}
+    }
+}
@@ -1,16 +1,53 @@
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::{
+    create_dir, create_symlink, dir_path, find_inode, find_inode_nofollow, link_file, make_pipe,
+    open_file, read_symlink, unlink_file, OpenFlags, Stat,
+};
 use crate::sbi::console_getchar;
-use crate::task::{current_task, current_user_token, suspend_current_and_run_next};
-use crate::memory::{translated_byte_buffer, translated_str, UserBuffer};
+use crate::task::{
+    current_process, current_user_token, is_cwd_of_any_process, prepare_user_write,
+    suspend_current_and_run_next,
+};
+use crate::memory::{
+    copy_to_user, translated_byte_buffer, translated_refmut, translated_str, VirtAddr,
+};
 use crate::print;
+use alloc::sync::Arc;
+use easy_fs::Inode;
 
 const FD_STDIN: usize = 0;
 const FD_STDOUT: usize = 1;
+//dup3 中 newfd 的上限，避免用户传入过大的值使文件描述符表耗尽内核堆
+const FD_LIMIT: usize = 256;
+//*at 系列系统调用中表示从当前工作目录开始查找的 dirfd
+const AT_FDCWD: isize = -100;
+//fstatat 的 flags 中表示路径最后一级是符号链接时不跟随
+const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+//unlinkat 的 flags 中表示删除目录而不是文件
+const AT_REMOVEDIR: u32 = 0x200;
+
+//得到 *at 系列系统调用查找相对路径的起点：AT_FDCWD 表示当前工作目录，
+//否则 dirfd 必须是一个已打开的目录
+fn base_inode(dirfd: isize) -> Option<Arc<Inode>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if dirfd == AT_FDCWD {
+        return Some(inner.cwd.clone());
+    }
+    if dirfd < 0 || dirfd as usize >= inner.fd_table.len() {
+        return None;
+    }
+    let inode = inner.fd_table[dirfd as usize].as_ref()?.inode()?;
+    if inode.is_dir() {
+        Some(inode)
+    } else {
+        None
+    }
+}
 
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
         return -1;
     }
@@ -19,9 +56,13 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
         if !file.readable() {
             return -1;
         }
-        // release current task TCB manually to avoid multi-borrow
+        if !inner.memory_set.prepare_user_write(VirtAddr::from(buf as usize), len) {
+            return -1;
+        }
+        // release current process PCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.read(translated_byte_buffer(token, buf, len))
+            .map_or(-1, |size| size as isize)
     } else {
         -1
     }
@@ -29,8 +70,8 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
         return -1;
     }
@@ -39,20 +80,83 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
             return -1;
         }
         let file = file.clone();
-        // release current task TCB manually to avoid multi-borrow
+        // release current process PCB manually to avoid multi-borrow
         drop(inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.write(translated_byte_buffer(token, buf, len))
+            .map_or(-1, |size| size as isize)
+    } else {
+        -1
+    }
+}
+
+//移动文件的读写位置，返回新的位置
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        drop(inner);
+        file.seek(offset, whence).map_or(-1, |offset| offset as isize)
+    } else {
+        -1
+    }
+}
+
+//从文件的 offset 处读取，不改变文件的读写位置
+pub fn sys_pread64(fd: usize, buf: *const u8, len: usize, offset: usize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        if !file.readable() {
+            return -1;
+        }
+        if !inner.memory_set.prepare_user_write(VirtAddr::from(buf as usize), len) {
+            return -1;
+        }
+        drop(inner);
+        file.read_at(offset, translated_byte_buffer(token, buf, len))
+            .map_or(-1, |size| size as isize)
+    } else {
+        -1
+    }
+}
+
+//向文件的 offset 处写入，不改变文件的读写位置
+pub fn sys_pwrite64(fd: usize, buf: *const u8, len: usize, offset: usize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        if !file.writable() {
+            return -1;
+        }
+        drop(inner);
+        file.write_at(offset, translated_byte_buffer(token, buf, len))
+            .map_or(-1, |size| size as isize)
     } else {
         -1
     }
 }
 
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
-    let task = current_task().unwrap();
+    let process = current_process();
     let token = current_user_token();
     let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner_exclusive_access();
+    let cwd = process.inner_exclusive_access().cwd.clone();
+    if let Some(inode) = open_file(&cwd, path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
+        let mut inner = process.inner_exclusive_access();
         let fd = inner.alloc_fd();
         inner.fd_table[fd] = Some(inode);
         fd as isize
@@ -62,8 +166,8 @@ pub fn sys_open(path: *const u8, flags: u32) -> isize {
 }
 
 pub fn sys_close(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
         return -1;
     }
@@ -73,3 +177,230 @@ pub fn sys_close(fd: usize) -> isize {
     inner.fd_table[fd].take();
     0
 }
+
+//创建一个管道，将读端和写端的文件描述符依次写入用户的 pipe[0] 和 pipe[1]
+pub fn sys_pipe(pipe: *mut usize) -> isize {
+    let process = current_process();
+    let token = current_user_token();
+    let mut inner = process.inner_exclusive_access();
+    if !inner
+        .memory_set
+        .prepare_user_write(VirtAddr::from(pipe as usize), 2 * core::mem::size_of::<usize>())
+    {
+        return -1;
+    }
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd();
+    inner.fd_table[read_fd] = Some(pipe_read);
+    let write_fd = inner.alloc_fd();
+    inner.fd_table[write_fd] = Some(pipe_write);
+    *translated_refmut(token, pipe) = read_fd;
+    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
+    0
+}
+
+//复制一个文件描述符，新的文件描述符是当前最小的空闲编号，与 fd 共享同一个打开的文件
+pub fn sys_dup(fd: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    let file = match &inner.fd_table[fd] {
+        Some(file) => file.clone(),
+        None => return -1,
+    };
+    let new_fd = inner.alloc_fd();
+    inner.fd_table[new_fd] = Some(file);
+    new_fd as isize
+}
+
+//将 oldfd 复制到指定的 newfd ，newfd 原来打开的文件会被先关闭。oldfd 与 newfd 相同时返回 -1
+pub fn sys_dup3(oldfd: usize, newfd: usize, _flags: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if oldfd == newfd || oldfd >= inner.fd_table.len() || newfd >= FD_LIMIT {
+        return -1;
+    }
+    let file = match &inner.fd_table[oldfd] {
+        Some(file) => file.clone(),
+        None => return -1,
+    };
+    while inner.fd_table.len() <= newfd {
+        inner.fd_table.push(None);
+    }
+    inner.fd_table[newfd] = Some(file);
+    newfd as isize
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, _mode: u32) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    if let Some(base) = base_inode(dirfd) {
+        if create_dir(&base, path.as_str()).is_some() {
+            return 0;
+        }
+    }
+    -1
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let base = match base_inode(dirfd) {
+        Some(base) => base,
+        None => return -1,
+    };
+    let is_dir = flags & AT_REMOVEDIR != 0;
+    //不允许删除任何进程的当前工作目录，否则它的索引节点被回收之后还会被那个进程继续使用
+    if is_dir {
+        let target = find_inode_nofollow(&base, path.as_str());
+        if target.map_or(false, |inode| is_cwd_of_any_process(inode.inode_id())) {
+            return -1;
+        }
+    }
+    if unlink_file(&base, path.as_str(), is_dir) {
+        0
+    } else {
+        -1
+    }
+}
+
+//为 olddirfd/oldpath 指向的普通文件在 newdirfd/newpath 处建立一个硬链接
+pub fn sys_linkat(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+    _flags: u32,
+) -> isize {
+    let token = current_user_token();
+    let oldpath = translated_str(token, oldpath);
+    let newpath = translated_str(token, newpath);
+    let (old_base, new_base) = match (base_inode(olddirfd), base_inode(newdirfd)) {
+        (Some(old_base), Some(new_base)) => (old_base, new_base),
+        _ => return -1,
+    };
+    if link_file(&old_base, oldpath.as_str(), &new_base, newpath.as_str()) {
+        0
+    } else {
+        -1
+    }
+}
+
+//在 newdirfd/linkpath 处创建一个指向 target 的符号链接
+pub fn sys_symlinkat(target: *const u8, newdirfd: isize, linkpath: *const u8) -> isize {
+    let token = current_user_token();
+    let target = translated_str(token, target);
+    let linkpath = translated_str(token, linkpath);
+    if let Some(base) = base_inode(newdirfd) {
+        if create_symlink(&base, target.as_str(), linkpath.as_str()) {
+            return 0;
+        }
+    }
+    -1
+}
+
+//将符号链接指向的路径写入用户缓冲区，超出缓冲区的部分被截断，不添加 \0 。返回写入的字节数
+pub fn sys_readlinkat(dirfd: isize, path: *const u8, buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let target = match base_inode(dirfd).and_then(|base| read_symlink(&base, path.as_str())) {
+        Some(target) => target,
+        None => return -1,
+    };
+    let len = len.min(target.len());
+    if !prepare_user_write(buf as usize, len) {
+        return -1;
+    }
+    let buffer = translated_byte_buffer(token, buf, len);
+    for (dst, src) in buffer.into_iter().zip(target.bytes()) {
+        unsafe {
+            *dst = src;
+        }
+    }
+    len as isize
+}
+
+//将已打开文件的元数据写入用户的 Stat 结构体
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        if !inner
+            .memory_set
+            .prepare_user_write(VirtAddr::from(st as usize), core::mem::size_of::<Stat>())
+        {
+            return -1;
+        }
+        drop(inner);
+        copy_to_user(token, st, &file.stat());
+        0
+    } else {
+        -1
+    }
+}
+
+//将路径对应文件的元数据写入用户的 Stat 结构体，flags 包含 AT_SYMLINK_NOFOLLOW 时返回符号链接本身的元数据
+pub fn sys_fstatat(dirfd: isize, path: *const u8, st: *mut Stat, flags: u32) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let base = match base_inode(dirfd) {
+        Some(base) => base,
+        None => return -1,
+    };
+    let inode = if flags & AT_SYMLINK_NOFOLLOW != 0 {
+        find_inode_nofollow(&base, path.as_str())
+    } else {
+        find_inode(&base, path.as_str())
+    };
+    match inode {
+        Some(inode) if prepare_user_write(st as usize, core::mem::size_of::<Stat>()) => {
+            copy_to_user(token, st, &Stat::from_inode(&inode));
+            0
+        }
+        _ => -1,
+    }
+}
+
+pub fn sys_chdir(path: *const u8) -> isize {
+    let process = current_process();
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let mut inner = process.inner_exclusive_access();
+    match find_inode(&inner.cwd, path.as_str()) {
+        Some(inode) if inode.is_dir() => {
+            inner.cwd = inode;
+            0
+        }
+        _ => -1,
+    }
+}
+
+//将当前工作目录的绝对路径以 \0 结尾写入用户缓冲区，返回路径的长度
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let cwd = current_process().inner_exclusive_access().cwd.clone();
+    let path = match dir_path(&cwd) {
+        Some(path) => path,
+        None => return -1,
+    };
+    if path.len() + 1 > len {
+        return -1;
+    }
+    if !prepare_user_write(buf as usize, path.len() + 1) {
+        return -1;
+    }
+    let buffer = translated_byte_buffer(token, buf, path.len() + 1);
+    for (dst, src) in buffer.into_iter().zip(path.bytes().chain(Some(0))) {
+        unsafe {
+            *dst = src;
+        }
+    }
+    path.len() as isize
+}
@@ -0,0 +1,206 @@
+use crate::config::PAGE_SIZE;
+use crate::memory::{
+    copy_to_user, frame_stats, heap_stats, shm_get, shm_remove, shm_segment, FileMapping,
+    MapPermission,
+};
+use crate::task::{current_process, current_user_token, prepare_user_write};
+
+const PROT_READ: usize = 0x1;
+const PROT_WRITE: usize = 0x2;
+const PROT_EXEC: usize = 0x4;
+const MAP_SHARED: usize = 0x01;
+const MAP_PRIVATE: usize = 0x02;
+const MAP_ANONYMOUS: usize = 0x20;
+//shmat 的 flags 和 shmctl 的 cmd
+const SHM_RDONLY: usize = 0o10000;
+const IPC_RMID: usize = 0;
+
+//将 mmap 的 prot 转换为逻辑段的访问权限。RISC-V 的页表项不允许只写不读，所以可写的页面同时也是可读的；
+//PROT_NONE 对应的页表项没有 R/W/X 标志位，会被硬件当成指向下一级页表的节点，因此不支持
+fn prot_to_permission(prot: usize) -> Option<MapPermission> {
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 || prot == 0 {
+        return None;
+    }
+    let mut permission = MapPermission::U;
+    if prot & PROT_READ != 0 {
+        permission |= MapPermission::R;
+    }
+    if prot & PROT_WRITE != 0 {
+        permission |= MapPermission::R | MapPermission::W;
+    }
+    if prot & PROT_EXEC != 0 {
+        permission |= MapPermission::X;
+    }
+    Some(permission)
+}
+
+//支持匿名的私有映射（MAP_PRIVATE | MAP_ANONYMOUS，此时 fd 和 offset 被忽略），以及普通文件的共享映射（MAP_SHARED）
+//和私有映射（MAP_PRIVATE）。成功时返回映射的起始地址，否则返回 -1
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    let permission = match prot_to_permission(prot) {
+        Some(permission) => permission,
+        None => return -1,
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = if flags == MAP_PRIVATE | MAP_ANONYMOUS {
+        None
+    } else if flags == MAP_SHARED || flags == MAP_PRIVATE {
+        //文件中的偏移必须按页对齐。被映射的文件必须可读，可写的共享映射还要求文件可写
+        if offset % PAGE_SIZE != 0 {
+            return -1;
+        }
+        let file = match inner.fd_table.get(fd) {
+            Some(Some(file)) => file.clone(),
+            _ => return -1,
+        };
+        let cache = match file.page_cache() {
+            Some(cache) => cache,
+            None => return -1,
+        };
+        let shared = flags == MAP_SHARED;
+        if !file.readable()
+            || (shared && permission.contains(MapPermission::W) && !file.writable())
+        {
+            return -1;
+        }
+        Some(FileMapping {
+            file,
+            cache,
+            offset,
+            shared,
+        })
+    } else {
+        return -1;
+    };
+    inner
+        .memory_set
+        .mmap(start, len, permission, file)
+        .map_or(-1, |start| start as isize)
+}
+
+//解除 [start, start + len) 的映射，可以只解除某次 mmap 得到的区域中的一部分
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.munmap(start, len) {
+        0
+    } else {
+        -1
+    }
+}
+
+//sysinfo 返回给用户的内存使用情况，布局与 Linux 的 struct sysinfo 不同
+#[repr(C)]
+pub struct SysInfo {
+    pub total_frames: usize,//物理页帧的总数
+    pub free_frames: usize,//空闲的物理页帧个数
+    pub heap_total: usize,//内核堆当前的总字节数，会随着堆的扩展而增加
+    pub heap_used: usize,//内核堆中已经分配出去的字节数
+}
+
+//将物理页帧和内核堆的使用情况写入用户的 SysInfo 结构体
+pub fn sys_sysinfo(info: *mut SysInfo) -> isize {
+    if !prepare_user_write(info as usize, core::mem::size_of::<SysInfo>()) {
+        return -1;
+    }
+    let (total_frames, free_frames) = frame_stats();
+    let (heap_total, heap_used) = heap_stats();
+    copy_to_user(
+        current_user_token(),
+        info,
+        &SysInfo {
+            total_frames,
+            free_frames,
+            heap_total,
+            heap_used,
+        },
+    );
+    0
+}
+
+//将 [start, start + len) 中页面的访问权限修改为 prot ，区间中的每个页面都必须已经被映射
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    let permission = match prot_to_permission(prot) {
+        Some(permission) => permission,
+        None => return -1,
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.mprotect(start, len, permission) {
+        0
+    } else {
+        -1
+    }
+}
+
+//将 [start, start + len) 中共享文件映射被修改过的页面写回文件，flags 被忽略
+pub fn sys_msync(start: usize, len: usize, _flags: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.msync(start, len) {
+        0
+    } else {
+        -1
+    }
+}
+
+//将程序断点设置为 brk 并返回设置之后的程序断点。失败时程序断点保持不变，因此 brk 为 0 时可以用来查询当前的程序断点
+pub fn sys_brk(brk: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.memory_set.set_brk(brk) as isize
+}
+
+//获取 key 对应的共享内存段，flags 包含 IPC_CREAT 时在不存在时新建，key 为 IPC_PRIVATE 时总是新建。
+//成功时返回共享内存段的编号，否则返回 -1
+pub fn sys_shmget(key: usize, size: usize, flags: usize) -> isize {
+    shm_get(key, size, flags).map_or(-1, |id| id as isize)
+}
+
+//将编号为 shmid 的共享内存段映射到 addr 处，addr 为 0 时由内核选择位置，flags 包含 SHM_RDONLY 时只读映射。
+//成功时返回映射的起始地址，否则返回 -1
+pub fn sys_shmat(shmid: usize, addr: usize, flags: usize) -> isize {
+    let segment = match shm_segment(shmid) {
+        Some(segment) => segment,
+        None => return -1,
+    };
+    let permission = if flags & SHM_RDONLY != 0 {
+        MapPermission::R
+    } else {
+        MapPermission::R | MapPermission::W
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner
+        .memory_set
+        .attach_shm(addr, segment, permission)
+        .map_or(-1, |start| start as isize)
+}
+
+//解除 shmat 在 addr 处建立的共享内存段映射
+pub fn sys_shmdt(addr: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.detach_shm(addr) {
+        0
+    } else {
+        -1
+    }
+}
+
+//目前只支持 IPC_RMID ：删除共享内存段，已经建立的映射仍然有效，buf 被忽略
+pub fn sys_shmctl(shmid: usize, cmd: usize, _buf: usize) -> isize {
+    if cmd == IPC_RMID && shm_remove(shmid) {
+        0
+    } else {
+        -1
+    }
+}
@@ -1,11 +1,18 @@
+use crate::config::USER_STACK_SIZE;
 use crate::fs::{open_file, OpenFlags};
-use crate::memory::{translated_refmut, translated_str};
+use crate::memory::{
+    copy_from_user, copy_to_user, translated_ref, translated_refmut, translated_str, VirtAddr,
+};
 use crate::task::{
-    add_task, current_task, current_user_token, exit_current_and_run_next,
-    suspend_current_and_run_next,
+    current_process, current_task, current_user_token, exit_current_and_run_next, force_signal,
+    pid2process, prepare_user_read, send_signal, suspend_current_and_run_next, ProcessControlBlock,
+    SignalAction, SignalFlags, SignalFrame, INITPROC, NICE_MAX, NICE_MIN, SIG_BLOCK, SIG_SETMASK,
+    SIG_UNBLOCK,
 };
 use crate::timer::get_time_ms;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 //调用 sys_yield 主动交出使用权
 pub fn sys_yield() -> isize {
@@ -13,7 +20,7 @@ pub fn sys_yield() -> isize {
     0
 }
 
-//主动退出或者出错由内核终止
+//当前线程主动退出，主线程退出时整个进程随之退出
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
@@ -24,80 +31,319 @@ pub fn sys_get_time() -> isize {
 }
 
 pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+    current_process().getpid() as isize
 }
 
+//只有单线程的进程可以 fork ，子进程中只有主线程
 pub fn sys_fork() -> isize {
-    let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
-    let new_pid = new_task.pid.0;
+    let current_process = current_process();
+    if current_process.inner_exclusive_access().thread_count() > 1 {
+        return -1;
+    }
+    let new_process = current_process.fork();
+    let new_pid = new_process.getpid();
     // modify trap context of new_task, because it returns immediately after switching
+    let new_task = new_process.inner_exclusive_access().get_task(0).unwrap();
     let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     //将子进程的 Trap 上下文中用来存放系统调用返回值的 a0 寄存器修改为 0
     trap_cx.x[10] = 0;
-    // add new task to scheduler
-    add_task(new_task);
     new_pid as isize
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
+//从应用地址空间中读取一个以空指针结尾的字符串指针数组，ptr 为空指针时视为空数组
+fn translated_str_array(token: usize, mut ptr: *const usize) -> Vec<String> {
+    let mut strings = Vec::new();
+    if ptr.is_null() {
+        return strings;
+    }
+    loop {
+        let str_ptr = *translated_ref(token, ptr);
+        if str_ptr == 0 {
+            break;
+        }
+        strings.push(translated_str(token, str_ptr as *const u8));
+        unsafe {
+            ptr = ptr.add(1);
+        }
+    }
+    strings
+}
+
+//args 和 envs 分别是以空指针结尾的命令行参数和环境变量数组，它们会被复制到新的用户栈上。
+//与 fork 一样只有单线程的进程可以调用
+pub fn sys_exec(path: *const u8, args: *const usize, envs: *const usize) -> isize {
     let token = current_user_token();
     let path = translated_str(token, path);
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+    let args = translated_str_array(token, args);
+    let envs = translated_str_array(token, envs);
+    //参数和环境变量最多占用一半的用户栈，否则新程序的栈空间不够用
+    let args_size: usize = args
+        .iter()
+        .chain(envs.iter())
+        .map(|s| s.len() + 1 + core::mem::size_of::<usize>())
+        .sum();
+    if args_size > USER_STACK_SIZE / 2 {
+        return -1;
+    }
+    let process = current_process();
+    if process.inner_exclusive_access().thread_count() > 1 {
+        return -1;
+    }
+    let cwd = process.inner_exclusive_access().cwd.clone();
+    if let Some(app_inode) = open_file(&cwd, path.as_str(), OpenFlags::RDONLY) {
         let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
-        task.exec(all_data.as_slice());
-        0
+        let argc = args.len();
+        process.exec(all_data.as_slice(), args, envs);
+        // return argc because cx.x[10] will be covered with it later
+        argc as isize
     } else {
         -1
     }
 }
 
+//options 中只支持 WNOHANG ：子进程都还没有退出的时候立即返回 0 而不是阻塞等待
+const WNOHANG: usize = 1;
+
 //如果当前的进程不存在一个进程 ID 为 pid（pid==-1 或 pid > 0）的子进程，则返回 -1；如果存在一个进程 ID 为
-// pid 的僵尸子进程，则正常回收并返回子进程的 pid，并更新系统调用的退出码参数为 exit_code 。这里还有一个 -2 的
-//返回值，它的含义是子进程还没退出，通知用户库 user_lib （是实际发出系统调用的地方），这样用户库看到是 -2 后，
-//就进一步调用 sys_yield 系统调用，让当前父进程进入等待状态。
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+// pid 的僵尸子进程，则正常回收并返回子进程的 pid，并更新系统调用的退出码参数为 exit_code 。如果符合要求的
+//子进程都还没有退出，当前线程会阻塞在进程的 child_exit 等待队列上，直到有子进程退出时被唤醒再重新检查；
+//设置了 WNOHANG 时则直接返回 0 。
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
+    let task = current_task().unwrap();
+    let process = task.process();
+    loop {
+        // find a child process
+        let mut inner = process.inner_exclusive_access();
+        //判断 sys_waitpid 是否会返回 -1 ，这取决于当前进程是否有一个符合要求的子进程。当传入的 pid 为 -1 的时候，
+        //任何一个子进程都算是符合要求；但 pid 不为 -1 的时候，则只有 PID 恰好与 pid 相同的子进程才算符合条件
+        if !inner
+            .children
+            .iter()
+            .any(|p| pid == -1 || pid as usize == p.getpid())
+        {
+            return -1;
+            // ---- release current PCB
+        }
+        //先确认退出码能够写入，否则子进程被回收之后它的退出码就丢失了
+        if !exit_code_ptr.is_null()
+            && !inner.memory_set.prepare_user_write(
+                VirtAddr::from(exit_code_ptr as usize),
+                core::mem::size_of::<i32>(),
+            )
+        {
+            return -1;
+        }
+        //判断符合要求的子进程中是否有僵尸进程，如果有的话还需要同时找出它在当前进程控制块子进程向量中的下标
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB exclusively
+            p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB
+        });
+        if let Some((idx, _)) = pair {
+            //将子进程从向量中移除并置于当前上下文中
+            let child = inner.children.remove(idx);
+            //子进程的所有线程都已经退出，通常这是对该子进程控制块的最后一次强引用。当它所在的代码块结束，
+            //子进程控制块被回收，释放它占用的所有资源，包括：各个线程的内核栈和它的 PID 还有它的应用地址空间存放页表的那些物理页帧等等。
+            //即使别处暂时还持有引用，这些资源也会在最后一个引用释放时回收，所以这里不要求引用唯一
+            let found_pid = child.getpid();
+            // ++++ temporarily access child PCB exclusively
+            let exit_code = child.inner_exclusive_access().exit_code;
+            // ++++ release child PCB
+            //写入到当前进程的应用地址空间中。由于应用传递给内核的仅仅是一个指向应用地址空间中保存子进程返回值的内存区域的指针，
+            //我们还需要在 translated_refmut 中手动查页表找到应该写入到物理内存中的哪个位置，这样才能把子进程的退出码 exit_code 返回给父进程。
+            if !exit_code_ptr.is_null() {
+                *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
+            }
+            return found_pid as isize;
+        }
+        if options & WNOHANG != 0 {
+            return 0;
+        }
+        //收到需要处理的信号时不再等待，返回用户态去处理它
+        if inner.signal_pending(task.inner_exclusive_access().signal_mask) {
+            return -1;
+        }
+        drop(inner);
+        // ---- release current PCB
+        process.child_exit.wait();
+    }
+}
+
+//向进程标识符为 pid 的进程发送编号为 signum 的信号，signum 为 0 时只检查进程是否存在。
+//initproc 负责回收所有孤儿进程，不能向它发送信号
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    let process = match pid2process(pid) {
+        Some(process) if !Arc::ptr_eq(&process, &INITPROC) => process,
+        _ => return -1,
+    };
+    if signum == 0 {
+        return 0;
+    }
+    match SignalFlags::from_signum(signum) {
+        Some(signal) => {
+            send_signal(&process, signal);
+            0
+        }
+        None => -1,
+    }
+}
+
+//设置信号 signum 的处理方式，act 为空指针时只读取，oldact 不为空指针时写入原来的处理方式。
+//SIGKILL 和 SIGSTOP 的处理方式不能被修改
+pub fn sys_sigaction(signum: usize, act: *const SignalAction, oldact: *mut SignalAction) -> isize {
+    let signal = match SignalFlags::from_signum(signum) {
+        Some(signal) => signal,
+        None => return -1,
+    };
+    if !act.is_null() && SignalFlags::unblockable().contains(signal) {
+        return -1;
+    }
+    //读取用户空间可能需要换入页面，这要借用进程控制块，所以在借用它之前先读入新的处理方式
+    let token = current_user_token();
+    let action = if act.is_null() {
+        None
+    } else {
+        if !prepare_user_read(act as usize, core::mem::size_of::<SignalAction>()) {
+            return -1;
+        }
+        Some(copy_from_user(token, act))
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if !oldact.is_null() {
+        if !inner.memory_set.prepare_user_write(
+            VirtAddr::from(oldact as usize),
+            core::mem::size_of::<SignalAction>(),
+        ) {
+            return -1;
+        }
+        copy_to_user(token, oldact, &inner.signal_actions[signum]);
+    }
+    if let Some(mut action) = action {
+        action.mask =
+            SignalFlags::from_bits_truncate(action.mask.bits()) - SignalFlags::unblockable();
+        inner.signal_actions[signum] = action;
+        //被忽略的信号即使已经收到也直接丢弃
+        if inner.signal_ignored(signum) {
+            inner.signals.remove(signal);
+        }
+    }
+    0
+}
+
+//按照 how 修改信号屏蔽字：SIG_BLOCK 加入 set 中的信号，SIG_UNBLOCK 去掉 set 中的信号，SIG_SETMASK 替换为 set 。
+//set 为空指针时只读取，oldset 不为空指针时写入原来的屏蔽字。SIGKILL 和 SIGSTOP 不能被屏蔽。
+//屏蔽字属于调用它的线程
+pub fn sys_sigprocmask(how: usize, set: *const u32, oldset: *mut u32) -> isize {
+    //与 sigaction 一样在借用进程控制块之前读入 set
+    let token = current_user_token();
+    let set = if set.is_null() {
+        None
+    } else {
+        if !prepare_user_read(set as usize, core::mem::size_of::<u32>()) {
+            return -1;
+        }
+        Some(
+            SignalFlags::from_bits_truncate(*translated_ref(token, set))
+                - SignalFlags::unblockable(),
+        )
+    };
     let task = current_task().unwrap();
-    // find a child process
+    let process = task.process();
+    let mut process_inner = process.inner_exclusive_access();
+    let mut inner = task.inner_exclusive_access();
+    let old_mask = inner.signal_mask;
+    if let Some(set) = set {
+        inner.signal_mask = match how {
+            SIG_BLOCK => old_mask | set,
+            SIG_UNBLOCK => old_mask - set,
+            SIG_SETMASK => set,
+            _ => return -1,
+        };
+    }
+    if !oldset.is_null() {
+        if !process_inner
+            .memory_set
+            .prepare_user_write(VirtAddr::from(oldset as usize), core::mem::size_of::<u32>())
+        {
+            inner.signal_mask = old_mask;
+            return -1;
+        }
+        *translated_refmut(token, oldset) = old_mask.bits();
+    }
+    0
+}
 
+//从信号处理函数返回：用户栈顶保存着进入处理函数之前的现场，用它恢复 Trap 上下文和信号屏蔽字。
+//返回值会被写入 a0 ，所以返回恢复出来的 a0 ，以免覆盖被打断的执行流的 a0
+pub fn sys_sigreturn() -> isize {
+    let task = current_task().unwrap();
+    let process = task.process();
+    let mut process_inner = process.inner_exclusive_access();
     let mut inner = task.inner_exclusive_access();
-    //判断 sys_waitpid 是否会返回 -1 ，这取决于当前进程是否有一个符合要求的子进程。当传入的 pid 为 -1 的时候，
-    //任何一个子进程都算是符合要求；但 pid 不为 -1 的时候，则只有 PID 恰好与 pid 相同的子进程才算符合条件
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.getpid())
-    {
+    let cx = inner.get_trap_cx();
+    let frame_ptr = cx.x[2] as *const SignalFrame;
+    //栈顶不是一个可以访问的现场，说明用户栈已经被破坏
+    if !process_inner.memory_set.prepare_user_write(
+        VirtAddr::from(frame_ptr as usize),
+        core::mem::size_of::<SignalFrame>(),
+    ) {
+        drop(inner);
+        drop(process_inner);
+        force_signal(SignalFlags::SIGSEGV);
         return -1;
-        // ---- release current PCB
     }
-    //判断符合要求的子进程中是否有僵尸进程，如果有的话还需要同时找出它在当前进程控制块子进程向量中的下标。如果找不到的话直接返回 -2
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB exclusively
-        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB
-    });
-    if let Some((idx, _)) = pair {
-        //将子进程从向量中移除并置于当前上下文中
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after being removed from children list
-        //行确认这是对于该子进程控制块的唯一一次强引用，即它不会出现在某个进程的子进程向量中，更不会出现在处理器
-        //监控器或者任务管理器中。当它所在的代码块结束，这次引用变量的生命周期结束，将导致该子进程进程控制块的引用
-        //计数变为 0 ，彻底回收掉它占用的所有资源，包括：内核栈和它的 PID 还有它的应用地址空间存放页表的那些物理页帧等等。
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily access child PCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        //写入到当前进程的应用地址空间中。由于应用传递给内核的仅仅是一个指向应用地址空间中保存子进程返回值的内存区域的指针，
-        //我们还需要在 translated_refmut 中手动查页表找到应该写入到物理内存中的哪个位置，这样才能把子进程的退出码 exit_code 返回给父进程。
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
+    let frame = copy_from_user(process_inner.get_user_token(), frame_ptr);
+    cx.x = frame.x;
+    cx.sepc = frame.sepc;
+    inner.signal_mask =
+        SignalFlags::from_bits_truncate(frame.mask.bits()) - SignalFlags::unblockable();
+    cx.x[10] as isize
+}
+
+//setpriority 和 getpriority 的 which ：目前只支持按进程设置
+const PRIO_PROCESS: usize = 0;
+
+//who 为 0 时表示当前进程，否则是进程标识符
+fn priority_target(which: usize, who: usize) -> Option<Arc<ProcessControlBlock>> {
+    if which != PRIO_PROCESS {
+        return None;
+    }
+    if who == 0 {
+        Some(current_process())
     } else {
-        -2
+        pid2process(who)
     }
-    // ---- release current PCB automatically
-}
\ No newline at end of file
+}
+
+//将进程中所有线程的 nice 值设为 nice ，超出范围的值被截断到 [NICE_MIN, NICE_MAX] 。
+//之后创建的线程和 fork 出的子进程继承这个值
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    let process = match priority_target(which, who) {
+        Some(process) => process,
+        None => return -1,
+    };
+    let nice = nice.clamp(NICE_MIN as isize, NICE_MAX as isize) as i32;
+    let inner = process.inner_exclusive_access();
+    for task in inner.tasks.iter().flatten() {
+        task.inner_exclusive_access().nice = nice;
+    }
+    0
+}
+
+//返回进程主线程的 nice 值。与 Linux 的系统调用一样返回 20 - nice ，使得结果总是正数，不会与错误混淆
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    let process = match priority_target(which, who) {
+        Some(process) => process,
+        None => return -1,
+    };
+    //主线程可能已经退出并被回收，取任意一个还在的线程，它们的 nice 值相同
+    let inner = process.inner_exclusive_access();
+    let nice = match inner.tasks.iter().flatten().next() {
+        Some(task) => task.inner_exclusive_access().nice,
+        None => return -1,
+    };
+    20 - nice as isize
+}
@@ -0,0 +1,169 @@
+use crate::memory::{PhysAddr, VirtAddr};
+use crate::sync::{futex_wait, futex_wake, Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore};
+use crate::task::{current_process, current_task};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+//所有 futex 都按物理地址区分，进程私有的 futex 不需要特殊处理，忽略这个标志
+const FUTEX_PRIVATE_FLAG: usize = 128;
+
+//将对象放入列表中的第一个空位，没有空位时追加到末尾，返回它的编号
+fn insert_object<T: ?Sized>(list: &mut Vec<Option<Arc<T>>>, object: Arc<T>) -> usize {
+    if let Some(id) = (0..list.len()).find(|id| list[*id].is_none()) {
+        list[id] = Some(object);
+        id
+    } else {
+        list.push(Some(object));
+        list.len() - 1
+    }
+}
+
+//按编号取出对象之后立即释放进程控制块的借用，因为接下来的操作可能会阻塞
+fn get_mutex(id: usize) -> Option<Arc<dyn Mutex>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.mutex_list.get(id).cloned().flatten()
+}
+
+fn get_semaphore(id: usize) -> Option<Arc<Semaphore>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.semaphore_list.get(id).cloned().flatten()
+}
+
+fn get_condvar(id: usize) -> Option<Arc<Condvar>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.condvar_list.get(id).cloned().flatten()
+}
+
+//创建一个互斥锁，blocking 为真时是阻塞锁，否则是让出处理器的自旋锁。返回锁的编号
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    insert_object(&mut inner.mutex_list, mutex) as isize
+}
+
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    match get_mutex(mutex_id) {
+        Some(mutex) if mutex.lock() => 0,
+        _ => -1,
+    }
+}
+
+//释放一个没有被持有的锁返回 -1
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    match get_mutex(mutex_id) {
+        Some(mutex) if mutex.unlock() => 0,
+        _ => -1,
+    }
+}
+
+//创建一个初始计数为 res_count 的信号量，返回它的编号
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    insert_object(
+        &mut inner.semaphore_list,
+        Arc::new(Semaphore::new(res_count)),
+    ) as isize
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    match get_semaphore(sem_id) {
+        Some(sem) => {
+            sem.up();
+            0
+        }
+        None => -1,
+    }
+}
+
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    match get_semaphore(sem_id) {
+        Some(sem) if sem.down() => 0,
+        _ => -1,
+    }
+}
+
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    insert_object(&mut inner.condvar_list, Arc::new(Condvar::new())) as isize
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    match get_condvar(condvar_id) {
+        Some(condvar) => {
+            condvar.signal();
+            0
+        }
+        None => -1,
+    }
+}
+
+//释放编号为 mutex_id 的锁并在条件变量上等待，返回之前重新获取这个锁
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    match (get_condvar(condvar_id), get_mutex(mutex_id)) {
+        (Some(condvar), Some(mutex)) if condvar.wait(mutex) => 0,
+        _ => -1,
+    }
+}
+
+//找到用户变量 uaddr 所在的物理地址。先按写访问处理一次缺页：按需分配的页面在这里分配，
+//写时复制的页面在这里复制出私有的副本，之后这个地址才不会再变化，
+//否则父子进程各自的私有变量会被当作同一个 futex
+fn futex_addr(uaddr: usize) -> Option<PhysAddr> {
+    if uaddr % core::mem::size_of::<u32>() != 0 {
+        return None;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let va = VirtAddr::from(uaddr);
+    if !inner
+        .memory_set
+        .prepare_user_write(va, core::mem::size_of::<u32>())
+    {
+        return None;
+    }
+    let pte = inner.memory_set.translate(va.floor())?;
+    let aligned_pa: usize = PhysAddr::from(pte.ppn()).into();
+    Some(PhysAddr::from(aligned_pa + va.page_offset()))
+}
+
+//FUTEX_WAIT：uaddr 处的值仍为 val 时阻塞，直到其它线程在同一个物理地址上 FUTEX_WAKE ，返回 0 ；
+//值已经改变或者有需要处理的信号时立即返回 -1 ，用户程序应当重新检查条件。
+//检查和阻塞之间不会有其它任务运行，所以不会错过唤醒。
+//FUTEX_WAKE：唤醒最多 val 个在 uaddr 上等待的任务，返回唤醒的数量
+pub fn sys_futex(uaddr: usize, futex_op: usize, val: usize) -> isize {
+    let pa = match futex_addr(uaddr) {
+        Some(pa) => pa,
+        None => return -1,
+    };
+    match futex_op & !FUTEX_PRIVATE_FLAG {
+        FUTEX_WAIT => {
+            if *pa.get_mut::<u32>() != val as u32 {
+                return -1;
+            }
+            let task = current_task().unwrap();
+            let process = task.process();
+            let mask = task.inner_exclusive_access().signal_mask;
+            if process.inner_exclusive_access().signal_pending(mask) {
+                return -1;
+            }
+            drop(process);
+            drop(task);
+            futex_wait(pa);
+            0
+        }
+        FUTEX_WAKE => futex_wake(pa, val) as isize,
+        _ => -1,
+    }
+}
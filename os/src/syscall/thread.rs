@@ -0,0 +1,87 @@
+use crate::memory::{translated_refmut, VirtAddr, KERNEL_SPACE};
+use crate::task::{add_task, current_task, TaskControlBlock, TaskUserRes};
+use crate::trap::{trap_handler, TrapContext};
+use alloc::sync::Arc;
+
+//在当前进程中创建一个新线程，它从 entry 开始执行，参数 arg 通过 a0 传入，并继承当前线程的信号屏蔽字和 nice 值。
+//线程函数不能直接返回，必须调用 exit 退出。成功时返回新线程的编号
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    let task = current_task().unwrap();
+    let process = task.process();
+    //新线程的用户栈所在的位置已经被 mmap 占用时失败
+    let res = match TaskUserRes::new(&process, true) {
+        Some(res) => res,
+        None => return -1,
+    };
+    let tid = res.tid;
+    let ustack_top = res.ustack_top();
+    let task_inner = task.inner_exclusive_access();
+    let (signal_mask, nice) = (task_inner.signal_mask, task_inner.nice);
+    drop(task_inner);
+    let new_task = Arc::new(TaskControlBlock::new(&process, res, signal_mask, nice));
+    let mut process_inner = process.inner_exclusive_access();
+    //线程编号会被回收再利用，下标处可能已经有空位
+    while process_inner.tasks.len() <= tid {
+        process_inner.tasks.push(None);
+    }
+    process_inner.tasks[tid] = Some(new_task.clone());
+    drop(process_inner);
+    let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
+    *trap_cx = TrapContext::app_init_context(
+        entry,
+        ustack_top,
+        KERNEL_SPACE.exclusive_access().token(),
+        new_task.kernel_stack.get_top(),
+        trap_handler as usize,
+    );
+    trap_cx.x[10] = arg;
+    add_task(new_task);
+    tid as isize
+}
+
+pub fn sys_gettid() -> isize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .tid as isize
+}
+
+//等待同一进程中编号为 tid 的线程退出并回收它，它的退出码写入 exit_code_ptr 。线程不存在或者等待的是自己时
+//返回 -1 ；线程还没有退出时阻塞在进程的 thread_exit 等待队列上，期间收到需要处理的信号也返回 -1 。
+//成功时返回 tid ，之后这个线程编号可以被新的线程使用
+pub fn sys_waittid(tid: usize, exit_code_ptr: *mut i32) -> isize {
+    let task = current_task().unwrap();
+    let process = task.process();
+    loop {
+        let mut inner = process.inner_exclusive_access();
+        let waited = match inner.get_task(tid) {
+            Some(waited) if !Arc::ptr_eq(&waited, &task) => waited,
+            _ => return -1,
+        };
+        let exit_code = waited.inner_exclusive_access().exit_code;
+        if let Some(exit_code) = exit_code {
+            if !exit_code_ptr.is_null() {
+                if !inner.memory_set.prepare_user_write(
+                    VirtAddr::from(exit_code_ptr as usize),
+                    core::mem::size_of::<i32>(),
+                ) {
+                    return -1;
+                }
+                *translated_refmut(inner.get_user_token(), exit_code_ptr) = exit_code;
+            }
+            //释放线程控制块和它的内核栈，线程编号也可以再次分配
+            inner.tasks[tid] = None;
+            inner.dealloc_tid(tid);
+            return tid as isize;
+        }
+        //收到需要处理的信号时不再等待，返回用户态去处理它
+        if inner.signal_pending(task.inner_exclusive_access().signal_mask) {
+            return -1;
+        }
+        drop(inner);
+        process.thread_exit.wait();
+    }
+}
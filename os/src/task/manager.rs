@@ -1,39 +1,95 @@
-use super::{TaskControlBlock};
-use alloc::collections::{VecDeque};
+use super::scheduler::{DefaultScheduler, Scheduler};
+use super::{ProcessControlBlock, TaskControlBlock};
+use alloc::collections::BTreeMap;
 use crate::sync::UPSafeCell;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 
+//就绪任务的管理器，具体选择哪个任务运行由编译时选定的调度策略决定
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    scheduler: DefaultScheduler,
 }
 
-/// A simple FIFO scheduler.
 impl TaskManager {
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            scheduler: DefaultScheduler::new(),
         }
     }
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        self.scheduler.add(task);
     }
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+        self.scheduler.fetch()
     }
 }
 
 lazy_static! {
     pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
         unsafe { UPSafeCell::new(TaskManager::new()) };
+    //从进程标识符找到还没有退出的进程，kill 需要用到
+    pub static ref PID2PCB: UPSafeCell<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+    //上一次被换出页面的进程
+    static ref LAST_SWAPPED_PID: UPSafeCell<usize> = unsafe { UPSafeCell::new(0) };
 }
 
-//将一个任务加入队尾
+//将一个就绪的任务交给调度器
 pub fn add_task(task: Arc<TaskControlBlock>) {
     TASK_MANAGER.exclusive_access().add(task);
 }
 
-//从队头中取出一个任务来执行
+//按照调度策略取出下一个要执行的任务
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.exclusive_access().fetch()
 }
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PCB.exclusive_access().get(&pid).cloned()
+}
+
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PCB.exclusive_access().insert(pid, process);
+}
+
+pub fn remove_from_pid2process(pid: usize) {
+    PID2PCB.exclusive_access().remove(&pid);
+}
+
+//是否有还没有退出的进程以编号为 inode_id 的目录作为当前工作目录
+pub fn is_cwd_of_any_process(inode_id: u32) -> bool {
+    PID2PCB
+        .exclusive_access()
+        .values()
+        .any(|process| process.inner_exclusive_access().cwd.inode_id() == inode_id)
+}
+
+//物理内存不足时从某个进程的地址空间中换出一个页面。从上一次被换出页面的进程之后开始按进程标识符轮流选择，
+//这样换出的压力不会总落在同一个进程上。内核正在使用的地址空间跳过，返回 false 说明没有可以换出的页面
+pub fn swap_out_any() -> bool {
+    let processes: Vec<(usize, Arc<ProcessControlBlock>)> = match PID2PCB.try_exclusive_access() {
+        Some(pid2pcb) => pid2pcb
+            .iter()
+            .map(|(pid, process)| (*pid, process.clone()))
+            .collect(),
+        None => return false,
+    };
+    let last = *LAST_SWAPPED_PID.exclusive_access();
+    let start = processes
+        .iter()
+        .position(|(pid, _)| *pid > last)
+        .unwrap_or(0);
+    for i in 0..processes.len() {
+        let (pid, process) = &processes[(start + i) % processes.len()];
+        let swapped = match process.try_inner_exclusive_access() {
+            Some(mut inner) => inner.memory_set.swap_out_one(),
+            None => false,
+        };
+        if swapped {
+            *LAST_SWAPPED_PID.exclusive_access() = *pid;
+            return true;
+        }
+    }
+    false
+}
@@ -3,36 +3,52 @@ mod processor;
 mod switch;
 mod context;
 mod pid;
+mod process;
+mod scheduler;
+mod signal;
 #[allow(clippy::module_inception)]
 
 mod task;
 
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::{open_file, OpenFlags, ROOT_INODE};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
-pub use manager::{fetch_task,TaskManager};
+pub use manager::{
+    fetch_task, insert_into_pid2process, is_cwd_of_any_process, pid2process, swap_out_any,
+    TaskManager,
+};
 use switch::__switch;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use process::ProcessControlBlock;
+pub use scheduler::{NICE_MAX, NICE_MIN};
+pub use task::{TaskControlBlock, TaskStatus, TaskUserRes};
 
 pub use context::TaskContext;
 pub use manager::add_task;
-pub use pid::{pid_alloc, KernelStack, PidHandle,PidAllocator};
+use manager::remove_from_pid2process;
+pub use pid::{pid_alloc, KernelStack, PidHandle, RecycleAllocator};
+pub use signal::{
+    current_signal_pending, force_signal, handle_signals, send_signal, sigkill_pending,
+    SignalAction, SignalFlags, SignalFrame, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK,
+};
 pub use processor::{
-    current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,Processor
+    current_process, current_task, current_trap_cx, current_trap_cx_user_va, current_user_token,
+    pin_user_page, prepare_user_read, prepare_user_write, run_tasks, schedule, take_current_task,
+    Processor,
 };
 
-//初始化初始进程的进程控制块 INITPROC
+//初始化初始进程的进程控制块 INITPROC ，创建时它的主线程就已经加入了就绪队列
 lazy_static! {
-    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
-        let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
+    pub static ref INITPROC: Arc<ProcessControlBlock> = {
+        let inode = open_file(&ROOT_INODE, "initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
-        TaskControlBlock::new(v.as_slice())
-    });
+        ProcessControlBlock::new(v.as_slice())
+    };
 }
 
 ///Add init process to the manager
 pub fn add_initproc() {
-    add_task(INITPROC.clone());
+    lazy_static::initialize(&INITPROC);
 }
 
 pub fn suspend_current_and_run_next() {
@@ -50,37 +66,151 @@ pub fn suspend_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+//将当前任务标记为阻塞并切换到下一个任务。阻塞的任务不在就绪队列中，调用者需要事先把它放进某个
+//等待队列，之后由 wakeup_task 把它重新加入就绪队列
+pub fn block_current_and_run_next() {
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    task_inner.task_status = TaskStatus::Blocked;
+    drop(task_inner);
+    drop(task);
+    schedule(task_cx_ptr);
+}
+
+//唤醒一个阻塞的任务，将它放回就绪队列。任务可能已经被信号提前唤醒，这时什么也不做，返回 false
+pub fn wakeup_task(task: Arc<TaskControlBlock>) -> bool {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status != TaskStatus::Blocked {
+        return false;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    drop(task_inner);
+    add_task(task);
+    true
+}
+
+
+//当前线程退出。主线程或者进程中最后一个线程退出时整个进程随之退出
 pub fn exit_current_and_run_next(exit_code: i32) {
-    //将当前进程控制块从处理器监控 PROCESSOR 中取出而不是得到一份拷贝，这是为了正确维护进程控制块的引用计数
+    exit_current(exit_code, false);
+}
+
+//当前线程所在的整个进程退出，例如被信号终止的时候
+pub fn exit_current_process_and_run_next(exit_code: i32) {
+    exit_current(exit_code, true);
+}
+
+fn exit_current(exit_code: i32, whole_process: bool) {
+    //将当前线程控制块从处理器监控 PROCESSOR 中取出而不是得到一份拷贝，这是为了正确维护线程控制块的引用计数
     let task = take_current_task().unwrap();
-     // **** access current TCB exclusively
-    let mut inner = task.inner_exclusive_access();
-     // Change status to Zombie
-    inner.task_status = TaskStatus::Zombie;
+    let process = task.process();
+    // **** access current TCB exclusively
+    let mut task_inner = task.inner_exclusive_access();
+    let tid = task_inner.res.as_ref().unwrap().tid;
+    //释放线程的用户栈和 Trap 上下文，之后不会再返回用户态
+    let res = task_inner.res.take();
+    drop(task_inner);
+    drop(res);
+    let mut inner = process.inner_exclusive_access();
+    //主线程退出或者进程被终止时整个进程开始退出。其余线程可能正在执行系统调用，不能直接结束它们，
+    //否则它们内核栈上持有的进程控制块、管道等引用永远不会被释放。这里只唤醒阻塞的线程，
+    //它们在等待的中途或者回到用户态之前看到进程正在退出，就会以进程的退出码各自退出
+    if (whole_process || tid == 0) && !inner.exiting {
+        inner.exiting = true;
+        inner.exit_code = exit_code;
+    }
+    let exit_code = if inner.exiting {
+        inner.exit_code
+    } else {
+        exit_code
+    };
+    // Change status to Zombie and record exit code
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.task_status = TaskStatus::Zombie;
+    task_inner.exit_code = Some(exit_code);
+    drop(task_inner);
+    let blocked: Vec<_> = if inner.exiting {
+        inner
+            .tasks
+            .iter()
+            .flatten()
+            .filter(|task| task.inner_exclusive_access().task_status == TaskStatus::Blocked)
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let last = inner.thread_count() == 0;
+    drop(inner);
+    for task in blocked {
+        wakeup_task(task);
+    }
+    //最后一个线程退出时整个进程随之退出
+    if last {
+        exit_process(&process, &task, exit_code);
+    } else {
+        process.thread_exit.wake_all();
+    }
+    // drop task manually to maintain rc correctly
+    drop(process);
+    drop(task);
+    // we do not have to save task context
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
 
+//进程中的所有线程都已经退出：子进程交给 initproc ，释放地址空间中的数据和打开的文件。
+//current 是最后退出的当前线程，它还在使用自己的内核栈，它的线程控制块要等到进程被回收时才释放
+fn exit_process(
+    process: &Arc<ProcessControlBlock>,
+    current: &Arc<TaskControlBlock>,
+    exit_code: i32,
+) {
+    // **** access current PCB exclusively
+    let mut inner = process.inner_exclusive_access();
+    inner.is_zombie = true;
     // record exit code
     inner.exit_code = exit_code;
+    //已经退出的进程不能再通过 kill 找到
+    remove_from_pid2process(process.getpid());
     // do not move to its parent but under initproc
-  
-    // ++++++ access initproc TCB exclusively
+
+    // ++++++ access initproc PCB exclusively
+    let mut zombie_adopted = false;
     {
         let mut initproc_inner = INITPROC.inner_exclusive_access();
         for child in inner.children.iter() {
-            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+            let mut child_inner = child.inner_exclusive_access();
+            child_inner.parent = Some(Arc::downgrade(&INITPROC));
+            zombie_adopted |= child_inner.is_zombie;
             initproc_inner.children.push(child.clone());
         }
     }
-    // ++++++ release parent PCB
-    
+    // ++++++ release initproc PCB
+    //已经退出的子进程交给了 initproc ，需要唤醒可能正在等待的 initproc 来回收它们
+    if zombie_adopted {
+        INITPROC.child_exit.wake_all();
+    }
+    //唤醒在 waitpid 中等待的父进程，并向它发送 SIGCHLD
+    if let Some(parent) = inner.parent.as_ref().and_then(|parent| parent.upgrade()) {
+        parent.child_exit.wake_all();
+        send_signal(&parent, SignalFlags::SIGCHLD);
+    }
     inner.children.clear();
+    //其余线程的内核栈可以立即回收
+    inner.tasks.iter_mut().for_each(|task| {
+        if !task.as_ref().map_or(false, |task| Arc::ptr_eq(task, current)) {
+            *task = None;
+        }
+    });
+    //不再引用进程中的锁、信号量和条件变量，fork 时共享它们的其它进程仍然可以继续使用
+    inner.mutex_list.clear();
+    inner.semaphore_list.clear();
+    inner.condvar_list.clear();
     // deallocate user space
     inner.memory_set.recycle_data_pages();
-    drop(inner);
+    //立即关闭所有文件，这样管道的另一端不必等到父进程回收僵尸进程才能读到文件末尾
+    inner.fd_table.clear();
     // **** release current PCB
-    // drop task manually to maintain rc correctly
-    drop(task);
-    // we do not have to save task context
-    
-    let mut _unused = TaskContext::zero_init();
-    schedule(&mut _unused as *mut _);
 }
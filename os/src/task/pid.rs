@@ -6,45 +6,51 @@ use crate::sync::UPSafeCell;
 
 pub struct PidHandle(pub usize);
 
-pub struct PidAllocator {
+//分配可回收的编号，用于进程标识符、内核栈以及进程中线程的编号
+#[derive(Clone)]
+pub struct RecycleAllocator {
     current: usize,
     recycled: Vec<usize>,
 }
 
-impl PidAllocator {
+impl RecycleAllocator {
     pub fn new() -> Self {
-        PidAllocator {
+        RecycleAllocator {
             current: 0,
             recycled: Vec::new(),
         }
     }
-    pub fn alloc(&mut self) -> PidHandle {
-        if let Some(pid) = self.recycled.pop() {
-            PidHandle(pid)
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
         } else {
             self.current += 1;
-            PidHandle(self.current - 1)
+            self.current - 1
         }
     }
-    pub fn dealloc(&mut self, pid: usize) {
-        assert!(pid < self.current);
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
         assert!(
-            self.recycled.iter().find(|ppid| **ppid == pid).is_none(),
-            "pid {} has been deallocated!", pid
+            self.recycled.iter().find(|pid| **pid == id).is_none(),
+            "id {} has been deallocated!", id
         );
-        self.recycled.push(pid);
+        self.recycled.push(id);
     }
 }
 
 lazy_static! {
-    static ref PID_ALLOCATOR : UPSafeCell<PidAllocator> = unsafe {
-        UPSafeCell::new(PidAllocator::new())
+    static ref PID_ALLOCATOR : UPSafeCell<RecycleAllocator> = unsafe {
+        UPSafeCell::new(RecycleAllocator::new())
+    };
+    //每个线程都有自己的内核栈，内核栈的编号与进程标识符无关
+    static ref KSTACK_ALLOCATOR : UPSafeCell<RecycleAllocator> = unsafe {
+        UPSafeCell::new(RecycleAllocator::new())
     };
 }
 
 //分配出去一个将usize包装之后的PidHandle
 pub fn pid_alloc() -> PidHandle {
-    PID_ALLOCATOR.exclusive_access().alloc()
+    PidHandle(PID_ALLOCATOR.exclusive_access().alloc())
 }
 
 //允许编译器进行自动的资源回收
@@ -55,25 +61,25 @@ impl Drop for PidHandle {
 }
 
 /// Return (bottom, top) of a kernel stack in kernel space.
-pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+pub fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - kstack_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
 
 //之前将每个应用的内核栈按照应用编号从小到大的顺序将它们作为逻辑段从高地址到
 //低地址放在内核地址空间中，且两两之间保留一个守护页面使得我们能够尽可能
-//早的发现内核栈溢出问题。从本章开始，我们将应用编号替换为进程标识符。
+//早的发现内核栈溢出问题。支持线程之后每个线程都有一个内核栈，因此使用单独分配的内核栈编号。
 pub struct KernelStack {
-    pid: usize,
+    id: usize,
 }
 
 impl KernelStack {
-    //从一个PidHandle，也就是一个已分配的进程标识符中对应生成一个内核栈KernelStack
-	    pub fn new(pid_handle: &PidHandle) -> Self {
-			let pid = pid_handle.0;
-            //根据进程标识符计算内核栈在内核地址空间中的位置
-			let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(pid);
+    //分配一个内核栈编号并生成对应的内核栈KernelStack
+	    pub fn new() -> Self {
+			let id = KSTACK_ALLOCATOR.exclusive_access().alloc();
+            //根据内核栈编号计算内核栈在内核地址空间中的位置
+			let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(id);
 			//将一个逻辑段插入内核地址空间 KERNEL_SPACE 中
             KERNEL_SPACE
 			    .exclusive_access()
@@ -82,9 +88,7 @@ impl KernelStack {
 				    kernel_stack_top.into(),
 					MapPermission::R | MapPermission::W,
 				);
-			KernelStack {
-				pid: pid_handle.0,
-			}
+			KernelStack { id }
 	   }
        //将一个类型为T的变量压入内核栈顶并返回其裸指针
        #[allow(unused)]
@@ -97,17 +101,18 @@ impl KernelStack {
 			ptr_mut
 		}
 		pub fn get_top(&self) -> usize {
-		    let (_, kernel_stack_top) = kernel_stack_position(self.pid);
+		    let (_, kernel_stack_top) = kernel_stack_position(self.id);
 	        kernel_stack_top
 		}
 }
 
 impl Drop for KernelStack {
 	    fn drop(&mut self) {
-		let (kernel_stack_bottom, _) = kernel_stack_position(self.pid);
+		let (kernel_stack_bottom, _) = kernel_stack_position(self.id);
 		let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
 		KERNEL_SPACE
 			.exclusive_access()          
             .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+		KSTACK_ALLOCATOR.exclusive_access().dealloc(self.id);
 	    }
 }
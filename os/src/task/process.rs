@@ -0,0 +1,335 @@
+//! 进程控制块：同一进程中的所有线程共享地址空间、文件描述符表、子进程以及信号的处理方式
+use super::signal::{
+    default_action, DefaultAction, SignalAction, SignalFlags, MAX_SIG, SIG_DFL, SIG_IGN,
+};
+use super::{add_task, insert_into_pid2process, pid_alloc, PidHandle, RecycleAllocator};
+use super::{TaskControlBlock, TaskUserRes};
+use crate::config::PAGE_SIZE;
+use crate::fs::{File, Stdin, Stdout, ROOT_INODE};
+use crate::memory::{translated_refmut, MemorySet, VirtAddr, KERNEL_SPACE};
+use crate::sync::{Condvar, Mutex, Semaphore, UPSafeCell, WaitQueue};
+use crate::trap::{trap_handler, TrapContext};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefMut;
+use easy_fs::Inode;
+
+//辅助向量 auxv 中用到的几个类型
+const AT_NULL: usize = 0;
+const AT_PAGESZ: usize = 6;
+const AT_ENTRY: usize = 9;
+const AUXV_LEN: usize = 3;
+
+const WORD_SIZE: usize = core::mem::size_of::<usize>();
+
+pub struct ProcessControlBlock {
+    // immutable
+    pub pid: PidHandle,
+    // mutable
+    inner: UPSafeCell<ProcessControlBlockInner>,
+    //在 waitpid 中等待子进程退出的线程，子进程退出时会唤醒它们
+    pub child_exit: WaitQueue,
+    //在 waittid 中等待同一进程中其它线程退出的线程
+    pub thread_exit: WaitQueue,
+}
+
+//在运行过程中可能发生变化的元数据
+pub struct ProcessControlBlockInner {
+    pub is_zombie: bool,
+    //进程正在退出：其余线程在等待的中途或者回到用户态之前会各自退出，最后一个线程退出时进程成为僵尸进程
+    pub exiting: bool,
+    pub memory_set: MemorySet,                     //应用地址空间
+    pub parent: Option<Weak<ProcessControlBlock>>, //指向当前进程的父进程
+    //将当前进程的所有子进程的进程控制块以Arc智能指针的形式保存在一个向量中
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    //当进程调用 exit 系统调用主动退出或者被信号终止的时候，它的退出码exit_code
+    //会被内核保存在它的进程控制块中，并等待它的父进程通过 waitpid 回收它的资源的同时也收集
+    //它的 PID 以及退出码
+    pub exit_code: i32,
+    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub cwd: Arc<Inode>,      //当前工作目录，相对路径从这里开始查找
+    pub signals: SignalFlags, //收到但还没有处理的信号，由进程中任意一个没有屏蔽它的线程处理
+    pub signal_actions: [SignalAction; MAX_SIG + 1], //每个信号的处理方式，下标为信号的编号
+    pub stopped: bool,        //被 SIGSTOP 等信号暂停，直到收到 SIGCONT 或 SIGKILL
+    //进程中的线程，下标为线程编号。已经退出的线程在被 waittid 回收之前仍留在这里
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    pub task_res_allocator: RecycleAllocator,
+    //用户程序创建的锁、信号量和条件变量，下标为它们的编号
+    pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+}
+
+impl ProcessControlBlockInner {
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+    pub fn alloc_tid(&mut self) -> usize {
+        self.task_res_allocator.alloc()
+    }
+    pub fn dealloc_tid(&mut self, tid: usize) {
+        self.task_res_allocator.dealloc(tid)
+    }
+    pub fn get_task(&self, tid: usize) -> Option<Arc<TaskControlBlock>> {
+        self.tasks.get(tid).cloned().flatten()
+    }
+    //还没有退出的线程数
+    pub fn thread_count(&self) -> usize {
+        self.tasks
+            .iter()
+            .flatten()
+            .filter(|task| task.inner_exclusive_access().exit_code.is_none())
+            .count()
+    }
+    //信号 signum 是否会被直接丢弃：处理方式为 SIG_IGN ，或者为 SIG_DFL 且默认动作是忽略
+    pub fn signal_ignored(&self, signum: usize) -> bool {
+        match self.signal_actions[signum].handler {
+            SIG_IGN => true,
+            SIG_DFL => default_action(signum) == DefaultAction::Ignore,
+            _ => false,
+        }
+    }
+    //对屏蔽字为 mask 的线程来说是否有需要处理的信号，阻塞的系统调用据此提前返回，让线程回到用户态去处理信号。
+    //进程正在退出时也提前返回，让线程回到用户态之前退出
+    pub fn signal_pending(&self, mask: SignalFlags) -> bool {
+        if self.exiting {
+            return true;
+        }
+        let pending = self.signals & !mask;
+        (1..=MAX_SIG).any(|signum| {
+            SignalFlags::from_signum(signum).map_or(false, |signal| pending.contains(signal))
+                && !self.signal_ignored(signum)
+        })
+    }
+}
+
+//在内核中手动生成的进程只有初始进程initproc，余下所有的进程都是它直接或间接fork出来的。
+//当一个子进程被fork出来之后，它可以调用exec系统调用来加载并执行另一个可执行文件。
+impl ProcessControlBlock {
+    pub fn inner_exclusive_access(&self) -> RefMut<'_, ProcessControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+    //进程控制块已经被借用（内核正在处理这个进程的系统调用或者页错误）时返回 None
+    pub fn try_inner_exclusive_access(&self) -> Option<RefMut<'_, ProcessControlBlockInner>> {
+        self.inner.try_exclusive_access()
+    }
+    //创建一个新的进程及其主线程，目前仅用于内核中手动创建唯一一个初始进程 initproc
+    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+        //解析应用的 ELF 执行文件得到应用地址空间 memory_set 以及应用的入口点 entry_point
+        let (memory_set, entry_point) = MemorySet::from_elf(elf_data);
+        let process = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: unsafe {
+                UPSafeCell::new(ProcessControlBlockInner {
+                    is_zombie: false,
+                    exiting: false,
+                    memory_set,
+                    parent: None,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    fd_table: vec![
+                        // 0 -> stdin
+                        Some(Arc::new(Stdin)),
+                        // 1 -> stdout
+                        Some(Arc::new(Stdout)),
+                        // 2 -> stderr
+                        Some(Arc::new(Stdout)),
+                    ],
+                    cwd: ROOT_INODE.clone(),
+                    signals: SignalFlags::empty(),
+                    signal_actions: [SignalAction::default(); MAX_SIG + 1],
+                    stopped: false,
+                    tasks: Vec::new(),
+                    task_res_allocator: RecycleAllocator::new(),
+                    mutex_list: Vec::new(),
+                    semaphore_list: Vec::new(),
+                    condvar_list: Vec::new(),
+                })
+            },
+            child_exit: WaitQueue::new(),
+            thread_exit: WaitQueue::new(),
+        });
+        //创建主线程，为它分配用户栈、 Trap 上下文和内核栈
+        let res = TaskUserRes::new(&process, true).unwrap();
+        let ustack_top = res.ustack_top();
+        let task = Arc::new(TaskControlBlock::new(&process, res, SignalFlags::empty(), 0));
+        //初始化主线程的 Trap 上下文，使得第一次进入用户态的时候
+        //能正确跳转到应用入口点并设置好用户栈，同时也保证在 Trap 的时候用户态能正确进入内核态。
+        let trap_cx = task.inner_exclusive_access().get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            ustack_top,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kernel_stack.get_top(),
+            trap_handler as usize,
+        );
+        process
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(task.clone()));
+        insert_into_pid2process(process.getpid(), process.clone());
+        add_task(task);
+        process
+    }
+
+    //当前进程 fork 出来一个与之几乎相同的子进程。调用者保证当前进程只剩下主线程，
+    //子进程中只有一个复制自它的主线程
+    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+        // ---- access parent PCB exclusively
+        let mut parent_inner = self.inner_exclusive_access();
+        // copy user space(include trap context)
+        let memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set);
+        // copy fd table
+        let new_fd_table = parent_inner.fd_table.clone();
+        let child = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: unsafe {
+                UPSafeCell::new(ProcessControlBlockInner {
+                    is_zombie: false,
+                    exiting: false,
+                    memory_set,
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    fd_table: new_fd_table,
+                    cwd: parent_inner.cwd.clone(),
+                    //子进程继承信号的处理方式，但不继承还没有处理的信号
+                    signals: SignalFlags::empty(),
+                    signal_actions: parent_inner.signal_actions,
+                    stopped: false,
+                    tasks: Vec::new(),
+                    task_res_allocator: RecycleAllocator::new(),
+                    //与文件描述符一样，子进程和父进程共享同一组锁、信号量和条件变量，可以配合共享内存使用
+                    mutex_list: parent_inner.mutex_list.clone(),
+                    semaphore_list: parent_inner.semaphore_list.clone(),
+                    condvar_list: parent_inner.condvar_list.clone(),
+                })
+            },
+            child_exit: WaitQueue::new(),
+            thread_exit: WaitQueue::new(),
+        });
+        // add child
+        parent_inner.children.push(child.clone());
+        let parent_task = parent_inner.get_task(0).unwrap();
+        let parent_task_inner = parent_task.inner_exclusive_access();
+        let (signal_mask, nice) = (parent_task_inner.signal_mask, parent_task_inner.nice);
+        drop(parent_task_inner);
+        drop(parent_inner);
+        //子进程的主线程沿用已经复制好的用户栈和 Trap 上下文，同时继承信号屏蔽字和 nice 值
+        let res = TaskUserRes::new(&child, false).unwrap();
+        let task = Arc::new(TaskControlBlock::new(&child, res, signal_mask, nice));
+        // modify kernel_sp in trap_cx
+        let trap_cx = task.inner_exclusive_access().get_trap_cx();
+        trap_cx.kernel_sp = task.kernel_stack.get_top();
+        child
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(task.clone()));
+        insert_into_pid2process(child.getpid(), child.clone());
+        add_task(task);
+        child
+    }
+
+    //用新的可执行文件替换当前进程的地址空间，调用者保证当前进程只剩下主线程。
+    //按照 RISC-V psABI 的约定在新的用户栈上依次放置 argc 、 argv 、 envp 和 auxv ，
+    //字符串本身放在它们的上方，返回时 sp 指向 argc 且按 16 字节对齐
+    pub fn exec(&self, elf_data: &[u8], args: Vec<String>, envs: Vec<String>) {
+        // memory_set with elf program headers/trampoline
+        let (mut memory_set, entry_point) = MemorySet::from_elf(elf_data);
+        //主线程的编号不变，在新的地址空间中重新映射它的用户栈和 Trap 上下文
+        let task = self.inner_exclusive_access().get_task(0).unwrap();
+        let mut task_inner = task.inner_exclusive_access();
+        let res = task_inner.res.as_ref().unwrap();
+        res.alloc_user_res(&mut memory_set);
+        let trap_cx_ppn = res.trap_cx_ppn(&memory_set);
+        let mut user_sp = res.ustack_top();
+        let token = memory_set.token();
+        //用户栈是按需分配的，先为要写入的部分分配物理页帧：字符串、各个数组以及对齐需要的空间
+        let strings_len: usize = args.iter().chain(envs.iter()).map(|s| s.len() + 1).sum();
+        let words = 1 + (args.len() + 1) + (envs.len() + 1) + AUXV_LEN * 2;
+        let stack_len = strings_len + words * WORD_SIZE + 16;
+        memory_set.prepare_user_write(VirtAddr::from(user_sp - stack_len), stack_len);
+        // push strings of args and envs onto the new user stack
+        let mut push_str = |string: &String| -> usize {
+            user_sp -= string.len() + 1;
+            let mut p = user_sp;
+            for c in string.as_bytes().iter().chain(core::iter::once(&0u8)) {
+                *translated_refmut(token, p as *mut u8) = *c;
+                p += 1;
+            }
+            user_sp
+        };
+        let arg_ptrs: Vec<usize> = args.iter().map(&mut push_str).collect();
+        let env_ptrs: Vec<usize> = envs.iter().map(&mut push_str).collect();
+        let auxv: [(usize, usize); AUXV_LEN] = [
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_ENTRY, entry_point),
+            (AT_NULL, 0),
+        ];
+        // argc, argv[] with NULL, envp[] with NULL and auxv pairs
+        user_sp -= words * WORD_SIZE;
+        user_sp -= user_sp % 16;
+        let argv_base = user_sp + WORD_SIZE;
+        let envp_base = argv_base + (arg_ptrs.len() + 1) * WORD_SIZE;
+        let stack_words = core::iter::once(arg_ptrs.len())
+            .chain(arg_ptrs.iter().copied())
+            .chain(core::iter::once(0))
+            .chain(env_ptrs.iter().copied())
+            .chain(core::iter::once(0))
+            .chain(auxv.iter().flat_map(|&(key, value)| [key, value]));
+        for (i, word) in stack_words.enumerate() {
+            *translated_refmut(token, (user_sp + i * WORD_SIZE) as *mut usize) = word;
+        }
+
+        // **** access inner exclusively
+        let mut inner = self.inner_exclusive_access();
+        // substitute memory_set
+        inner.memory_set = memory_set;
+        //已经退出但还没有被回收的线程随旧的地址空间一起丢弃，线程编号从主线程之后重新分配
+        inner.tasks.truncate(1);
+        inner.task_res_allocator = RecycleAllocator::new();
+        inner.alloc_tid();
+        //新的程序不知道原来的锁、信号量和条件变量
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        //原来的信号处理函数已经不存在了，恢复为默认动作，被忽略的信号仍然保持忽略
+        for action in inner.signal_actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+        drop(inner);
+        // update trap_cx ppn
+        task_inner.trap_cx_ppn = trap_cx_ppn;
+        // initialize trap_cx
+        let trap_cx = task_inner.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kernel_stack.get_top(),
+            trap_handler as usize,
+        );
+        //同时通过 a0 、 a1 、 a2 把 argc 、 argv 和 envp 交给用户库的 _start
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        trap_cx.x[12] = envp_base;
+        // **** release inner automatically
+    }
+
+    //以usize的形式返回当前进程的进程标识符
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+}
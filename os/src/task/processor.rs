@@ -1,6 +1,7 @@
 use super::__switch;
 use super::{fetch_task,TaskStatus};
-use super::{TaskContext ,TaskControlBlock};
+use super::{ProcessControlBlock, TaskContext, TaskControlBlock};
+use crate::memory::{FrameTracker, MapPermission, VirtAddr, VirtPageNum};
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
 use lazy_static::*;
@@ -77,12 +78,54 @@ pub fn current_task() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.exclusive_access().current()
 }
 
+//当前线程所在的进程
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    current_task().unwrap().process()
+}
+
 pub fn current_user_token() -> usize {
     let task = current_task().unwrap();
-    let token = task.inner_exclusive_access().get_user_token();
+    let token = task.get_user_token();
     token
 }
 
+//内核即将写入当前进程用户空间中的 [ptr, ptr + len) ，先解除其中写时复制页面的共享。
+//区间中有用户不能写入的页面时返回 false
+pub fn prepare_user_write(ptr: usize, len: usize) -> bool {
+    current_process()
+        .inner_exclusive_access()
+        .memory_set
+        .prepare_user_write(VirtAddr::from(ptr), len)
+}
+
+//内核即将读取当前进程用户空间中的 [ptr, ptr + len) ，先换入其中不在内存中的页面。
+//区间中有用户不能读取的页面时返回 false
+pub fn prepare_user_read(ptr: usize, len: usize) -> bool {
+    current_process()
+        .inner_exclusive_access()
+        .memory_set
+        .prepare_user_read(VirtAddr::from(ptr), len)
+}
+
+//内核手动查页表访问当前进程的用户空间时，为其中还没有被访问过的按需分配页面分配物理页帧，已经被换出的页面先换入。
+//返回页面所在的物理页帧，持有它期间页帧不会被换出，也不会随地址空间一起被释放。
+//token 不是当前进程的地址空间或者页面不属于任何逻辑段时返回 None
+pub fn pin_user_page(token: usize, vpn: VirtPageNum) -> Option<Arc<FrameTracker>> {
+    let process = current_process();
+    //调用者仍借用着进程控制块时无法在这里换入页面，调用者必须事先用 prepare_user_write 等准备好页面。
+    //借用期间其它任务也不能从这个地址空间中换出页面，所以不固定页帧也是安全的
+    let mut inner = process.try_inner_exclusive_access()?;
+    if inner.get_user_token() != token {
+        return None;
+    }
+    if !inner.memory_set.translate(vpn).map_or(false, |pte| pte.is_valid()) {
+        inner
+            .memory_set
+            .handle_page_fault(vpn, MapPermission::empty());
+    }
+    inner.memory_set.frame(vpn)
+}
+
 pub fn current_trap_cx() -> &'static mut TrapContext {
     current_task()
         .unwrap()
@@ -90,6 +133,17 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
         .get_trap_cx()
 }
 
+//当前线程的 Trap 上下文在用户地址空间中的位置
+pub fn current_trap_cx_user_va() -> usize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .trap_cx_user_va()
+}
+
 //当一个应用用尽了内核本轮分配给它的时间片或者它主动调用yield系统调用
 //交出 CPU 使用权之后，内核会调用 schedule 函数来切换到 idle控制流
 //并开启新一轮的任务调度
@@ -0,0 +1,34 @@
+//! Scheduling policies used by the task manager
+//!
+//! 调度策略在编译时通过 cargo feature 选择：`sched_fifo` 为先来先服务，`sched_stride` 为步长调度，
+//! 必须恰好打开其中一个。Makefile 通过 SCHED 选择，默认是先来先服务
+use super::TaskControlBlock;
+use alloc::sync::Arc;
+
+#[cfg(all(feature = "sched_fifo", feature = "sched_stride"))]
+compile_error!("features `sched_fifo` and `sched_stride` are mutually exclusive");
+#[cfg(not(any(feature = "sched_fifo", feature = "sched_stride")))]
+compile_error!("one of the features `sched_fifo` and `sched_stride` must be enabled");
+
+#[cfg(feature = "sched_fifo")]
+mod fifo;
+#[cfg(feature = "sched_stride")]
+mod stride;
+
+#[cfg(feature = "sched_fifo")]
+pub use fifo::FifoScheduler as DefaultScheduler;
+#[cfg(feature = "sched_stride")]
+pub use stride::StrideScheduler as DefaultScheduler;
+
+//nice 值的范围，越小优先级越高，新进程的 nice 值为 0 。先来先服务的调度策略不考虑 nice 值
+pub const NICE_MIN: i32 = -20;
+pub const NICE_MAX: i32 = 19;
+
+//调度策略：管理就绪的任务并决定下一个运行哪个任务。调用 add 时调用者可能持有进程控制块的借用，
+//所以调度策略只能借用任务控制块
+pub trait Scheduler {
+    //将一个就绪的任务加入调度器
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    //取出下一个要运行的任务
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+}
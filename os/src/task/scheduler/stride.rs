@@ -0,0 +1,62 @@
+use super::{Scheduler, NICE_MIN};
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+//每个 nice 值对应的权重，与 Linux 相同：nice 值每差 1 ，得到的处理器时间大约相差 25%
+#[rustfmt::skip]
+const NICE_TO_WEIGHT: [u64; 40] = [
+    /* -20 */ 88761, 71755, 56483, 46273, 36291,
+    /* -15 */ 29154, 23254, 18705, 14949, 11916,
+    /* -10 */ 9548, 7620, 6100, 4904, 3906,
+    /*  -5 */ 3121, 2501, 1991, 1586, 1277,
+    /*   0 */ 1024, 820, 655, 526, 423,
+    /*   5 */ 335, 272, 215, 172, 137,
+    /*  10 */ 110, 87, 70, 56, 45,
+    /*  15 */ 36, 29, 23, 18, 15,
+];
+
+const BIG_STRIDE: u64 = 1 << 32;
+
+//nice 值对应的步长，与权重成反比
+fn stride_from_nice(nice: i32) -> u64 {
+    BIG_STRIDE / NICE_TO_WEIGHT[(nice - NICE_MIN) as usize]
+}
+
+/// Stride scheduling.
+//每个任务有一个行程值 pass ，每次选择 pass 最小的任务运行，并让它的 pass 加上自己的步长，
+//于是任务得到的处理器时间与权重成正比。pass 相同时按加入的先后顺序运行。
+//任务加入调度器时 pass 至少提高到最近一次被选中的任务的 pass ，否则刚创建的任务或者阻塞了很久的任务
+//会凭借很小的 pass 长时间独占处理器。pass 用 64 位记录，实际运行中不会溢出
+pub struct StrideScheduler {
+    ready_queue: VecDeque<(u64, Arc<TaskControlBlock>)>,
+    min_pass: u64,
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+            min_pass: 0,
+        }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass = task_inner.pass.max(self.min_pass);
+        let pass = task_inner.pass;
+        drop(task_inner);
+        self.ready_queue.push_back((pass, task));
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let index = (0..self.ready_queue.len()).min_by_key(|index| self.ready_queue[*index].0)?;
+        let (pass, task) = self.ready_queue.remove(index).unwrap();
+        self.min_pass = pass;
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass = pass + stride_from_nice(task_inner.nice);
+        drop(task_inner);
+        Some(task)
+    }
+}
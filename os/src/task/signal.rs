@@ -0,0 +1,267 @@
+//! POSIX 风格的信号：每个进程记录收到但还没有处理的信号以及每个信号的处理方式，每个线程有自己的屏蔽字
+use super::{current_task, exit_current_process_and_run_next, suspend_current_and_run_next};
+use super::{wakeup_task, ProcessControlBlock, TaskStatus};
+use crate::memory::{copy_to_user, VirtAddr};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use bitflags::*;
+
+//信号的最大编号，编号与 Linux 相同
+pub const MAX_SIG: usize = 31;
+
+//特殊的处理函数地址：执行默认动作或者忽略信号
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+//sigprocmask 的 how
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+bitflags! {
+    //信号的集合，编号为 n 的信号对应第 n 位
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGXCPU = 1 << 24;
+        const SIGXFSZ = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF = 1 << 27;
+        const SIGWINCH = 1 << 28;
+        const SIGIO = 1 << 29;
+        const SIGPWR = 1 << 30;
+        const SIGSYS = 1 << 31;
+    }
+}
+
+impl SignalFlags {
+    //编号为 signum 的信号，编号不合法时返回 None
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum == 0 || signum > MAX_SIG {
+            return None;
+        }
+        Self::from_bits(1 << signum)
+    }
+    //集合中编号最小的信号
+    pub fn first(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.bits().trailing_zeros() as usize)
+        }
+    }
+    //不能被屏蔽、也不能被捕获或忽略的信号
+    pub fn unblockable() -> Self {
+        Self::SIGKILL | Self::SIGSTOP
+    }
+    //会让进程暂停的信号
+    pub fn stop_signals() -> Self {
+        Self::SIGSTOP | Self::SIGTSTP | Self::SIGTTIN | Self::SIGTTOU
+    }
+}
+
+//信号没有设置处理函数时的默认动作
+#[derive(Copy, Clone, PartialEq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+pub fn default_action(signum: usize) -> DefaultAction {
+    let signal = SignalFlags::from_bits_truncate(1 << signum);
+    if signal == SignalFlags::SIGCONT {
+        DefaultAction::Continue
+    } else if SignalFlags::stop_signals().contains(signal) {
+        DefaultAction::Stop
+    } else if (SignalFlags::SIGCHLD | SignalFlags::SIGURG | SignalFlags::SIGWINCH).contains(signal)
+    {
+        DefaultAction::Ignore
+    } else {
+        DefaultAction::Terminate
+    }
+}
+
+//sigaction 设置的信号处理方式，布局与用户库中的定义相同。
+//handler 是处理函数的地址或者 SIG_DFL/SIG_IGN ；restorer 是处理函数返回到的地址，它负责调用 sigreturn ；
+//mask 是处理函数执行期间额外屏蔽的信号，正在处理的信号本身总是会被屏蔽
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+//进入信号处理函数之前保存在用户栈上的现场，sigreturn 时由它恢复被打断的执行流和原来的信号屏蔽字。
+//只保存通用寄存器和 sepc ，sstatus 等字段不能交给用户修改
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalFrame {
+    pub x: [usize; 32],
+    pub sepc: usize,
+    pub mask: SignalFlags,
+}
+
+//向进程发送信号。进程中阻塞在某个等待队列上、而且没有屏蔽这个信号的线程都会被唤醒，让阻塞的系统调用提前返回
+pub fn send_signal(process: &Arc<ProcessControlBlock>, signal: SignalFlags) {
+    let mut inner = process.inner_exclusive_access();
+    //SIGCONT 总是让暂停的进程继续运行，并且和暂停信号互相抵消
+    if signal == SignalFlags::SIGCONT {
+        inner.signals.remove(SignalFlags::stop_signals());
+        inner.stopped = false;
+    } else if SignalFlags::stop_signals().contains(signal) {
+        inner.signals.remove(SignalFlags::SIGCONT);
+    }
+    inner.signals |= signal;
+    let waiting: Vec<_> = inner
+        .tasks
+        .iter()
+        .flatten()
+        .filter(|task| {
+            let task_inner = task.inner_exclusive_access();
+            task_inner.task_status == TaskStatus::Blocked
+                && inner.signal_pending(task_inner.signal_mask)
+        })
+        .cloned()
+        .collect();
+    drop(inner);
+    for task in waiting {
+        wakeup_task(task);
+    }
+}
+
+//当前线程是否有需要处理的信号，在内核中等待的系统调用据此提前返回，让线程回到用户态去处理信号
+pub fn current_signal_pending() -> bool {
+    let task = current_task().unwrap();
+    let mask = task.inner_exclusive_access().signal_mask;
+    let process = task.process();
+    let pending = process.inner_exclusive_access().signal_pending(mask);
+    pending
+}
+
+//当前线程执行出错时向它所在的进程发送 SIGSEGV/SIGILL 。这个信号被屏蔽或者忽略时出错的指令会被反复执行，
+//所以此时先恢复默认动作并解除屏蔽，让进程被终止
+pub fn force_signal(signal: SignalFlags) {
+    let task = current_task().unwrap();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let mut task_inner = task.inner_exclusive_access();
+    let signum = signal.first().unwrap();
+    if task_inner.signal_mask.contains(signal) || inner.signal_actions[signum].handler == SIG_IGN {
+        task_inner.signal_mask.remove(signal);
+        inner.signal_actions[signum] = SignalAction::default();
+    }
+    inner.signals |= signal;
+}
+
+//当前进程是否收到了 SIGKILL 或者正在退出。内核中的锁、信号量和条件变量在等待时不会被普通的信号打断，
+//但这时放弃等待，让线程尽快退出
+pub fn sigkill_pending() -> bool {
+    let process = current_task().unwrap().process();
+    let inner = process.inner_exclusive_access();
+    inner.exiting || inner.signals.contains(SignalFlags::SIGKILL)
+}
+
+//在返回用户态之前处理当前线程能够处理的信号：执行默认动作，或者在用户栈上保存现场并修改 Trap 上下文，
+//让线程回到用户态之后先执行信号处理函数，处理函数返回到 restorer 再通过 sigreturn 恢复现场。
+//每次只进入一个处理函数，其余的信号等到 sigreturn 返回用户态时再处理
+pub fn handle_signals() {
+    let exit_code = loop {
+        let task = current_task().unwrap();
+        let process = task.process();
+        let mut inner = process.inner_exclusive_access();
+        let mut task_inner = task.inner_exclusive_access();
+        //进程正在退出时，其余线程都在回到用户态之前随之退出
+        if inner.exiting {
+            break Some(inner.exit_code);
+        }
+        let pending = inner.signals & !task_inner.signal_mask;
+        //暂停的进程只响应 SIGKILL ，在此之前不断让出处理器
+        if inner.stopped && !pending.contains(SignalFlags::SIGKILL) {
+            drop(task_inner);
+            drop(inner);
+            drop(process);
+            drop(task);
+            suspend_current_and_run_next();
+            continue;
+        }
+        let signum = match pending.first() {
+            Some(signum) => signum,
+            None => break None,
+        };
+        let signal = SignalFlags::from_bits_truncate(1 << signum);
+        inner.signals.remove(signal);
+        let action = inner.signal_actions[signum];
+        match action.handler {
+            SIG_IGN => {}
+            SIG_DFL => match default_action(signum) {
+                //被信号终止的进程的退出码为信号编号的相反数
+                DefaultAction::Terminate => break Some(-(signum as i32)),
+                DefaultAction::Stop => inner.stopped = true,
+                DefaultAction::Ignore | DefaultAction::Continue => {}
+            },
+            handler => {
+                let cx = task_inner.get_trap_cx();
+                let frame = SignalFrame {
+                    x: cx.x,
+                    sepc: cx.sepc,
+                    mask: task_inner.signal_mask,
+                };
+                let frame_size = core::mem::size_of::<SignalFrame>();
+                let sp = cx.x[2].checked_sub(frame_size).map(|sp| sp & !0xf);
+                //用户栈已经不能写入，无法进入处理函数，只能像 SIGSEGV 的默认动作一样终止进程
+                if !sp.map_or(false, |sp| {
+                    inner
+                        .memory_set
+                        .prepare_user_write(VirtAddr::from(sp), frame_size)
+                }) {
+                    break Some(-(SignalFlags::SIGSEGV.first().unwrap() as i32));
+                }
+                let sp = sp.unwrap();
+                copy_to_user(inner.get_user_token(), sp as *mut SignalFrame, &frame);
+                cx.x[1] = action.restorer;
+                cx.x[2] = sp;
+                cx.x[10] = signum;
+                cx.sepc = handler;
+                task_inner.signal_mask |= action.mask | signal;
+                break None;
+            }
+        }
+    };
+    if let Some(exit_code) = exit_code {
+        exit_current_process_and_run_next(exit_code);
+    }
+}
@@ -1,215 +1,179 @@
+use super::signal::SignalFlags;
 use super::TaskContext;
-use super::{pid_alloc, KernelStack, PidHandle};
-use crate::config::TRAP_CONTEXT;
-use crate::fs::{File, Stdin, Stdout};
-use crate::memory::{MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use super::{KernelStack, ProcessControlBlock};
+use crate::config::{PAGE_SIZE, TRAP_CONTEXT, USER_STACK_SIZE, USER_STACK_TOP};
+use crate::memory::{MapPermission, MemorySet, PhysPageNum, VirtAddr};
 use crate::sync::UPSafeCell;
-use crate::trap::{trap_handler, TrapContext};
+use crate::trap::TrapContext;
 use alloc::sync::{Arc, Weak};
-use alloc::vec;
-use alloc::vec::Vec;
 use core::cell::RefMut;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum TaskStatus {
     Ready,
     Running,
+    Blocked,
     Zombie,
 }
 
+//线程 tid 的用户栈栈顶。各个线程的用户栈从 USER_STACK_TOP 开始向低地址依次排列，
+//两两之间保留一个守护页面
+fn ustack_top_from_tid(tid: usize) -> usize {
+    USER_STACK_TOP - tid * (USER_STACK_SIZE + PAGE_SIZE)
+}
+
+//线程 tid 的 Trap 上下文所在页面的起始地址，各个线程的 Trap 上下文从 TRAP_CONTEXT 开始向低地址依次排列
+fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+//线程在进程地址空间中占用的资源：线程编号、用户栈以及 Trap 上下文。
+//线程退出时释放用户栈和 Trap 上下文，线程编号要等到线程被 waittid 回收时才释放
+pub struct TaskUserRes {
+    pub tid: usize,
+    pub process: Weak<ProcessControlBlock>,
+}
+
+impl TaskUserRes {
+    //为进程中的新线程分配线程编号，alloc_user_res 为真时同时映射它的用户栈和 Trap 上下文。
+    //这些位置已经被用户的 mmap 占用时失败
+    pub fn new(process: &Arc<ProcessControlBlock>, alloc_user_res: bool) -> Option<Self> {
+        let mut process_inner = process.inner_exclusive_access();
+        let tid = process_inner.alloc_tid();
+        let res = Self {
+            tid,
+            process: Arc::downgrade(process),
+        };
+        if alloc_user_res && !res.alloc_user_res(&mut process_inner.memory_set) {
+            process_inner.dealloc_tid(tid);
+            //此时不能让 Drop 再去借用进程控制块
+            core::mem::forget(res);
+            return None;
+        }
+        Some(res)
+    }
+    //在地址空间中映射线程的用户栈和 Trap 上下文，用户栈按需分配
+    pub fn alloc_user_res(&self, memory_set: &mut MemorySet) -> bool {
+        let ustack_top = self.ustack_top();
+        if memory_set
+            .mmap(
+                ustack_top - USER_STACK_SIZE,
+                USER_STACK_SIZE,
+                MapPermission::R | MapPermission::W,
+                None,
+            )
+            .is_none()
+        {
+            return false;
+        }
+        let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
+        memory_set.insert_framed_area(
+            trap_cx_bottom.into(),
+            (trap_cx_bottom + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        );
+        true
+    }
+    pub fn ustack_top(&self) -> usize {
+        ustack_top_from_tid(self.tid)
+    }
+    //Trap 上下文在用户地址空间中的位置，返回用户态时交给 __restore
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_bottom_from_tid(self.tid)
+    }
+    //查页表找到 Trap 上下文被实际放在哪个物理页帧上
+    pub fn trap_cx_ppn(&self, memory_set: &MemorySet) -> PhysPageNum {
+        memory_set
+            .translate(VirtAddr::from(self.trap_cx_user_va()).into())
+            .unwrap()
+            .ppn()
+    }
+}
+
+//线程退出时从进程的地址空间中去掉它的用户栈和 Trap 上下文。进程已经被回收时地址空间也随之释放了，不需要再做什么
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.upgrade() {
+            let mut process_inner = process.inner_exclusive_access();
+            let ustack_top = self.ustack_top();
+            process_inner
+                .memory_set
+                .munmap(ustack_top - USER_STACK_SIZE, USER_STACK_SIZE);
+            process_inner
+                .memory_set
+                .unmap_page(VirtAddr::from(self.trap_cx_user_va()).into());
+        }
+    }
+}
+
 pub struct TaskControlBlock {
-	// immutable
-    //初始化之后就不再变化的元数据：直接放在任务控制块中
-	pub pid: PidHandle,
-	pub kernel_stack: KernelStack,
-	// mutable
-	inner: UPSafeCell<TaskControlBlockInner>,
+    // immutable
+    //初始化之后就不再变化的元数据：直接放在线程控制块中
+    pub process: Weak<ProcessControlBlock>,
+    pub kernel_stack: KernelStack,
+    // mutable
+    inner: UPSafeCell<TaskControlBlockInner>,
 }
 
 //在运行过程中可能发生变化的元数据
 pub struct TaskControlBlockInner {
+    //线程退出之后为 None
+    pub res: Option<TaskUserRes>,
     //应用地址空间中的Trap上下文被放在的物理页帧的物理页号
     pub trap_cx_ppn: PhysPageNum,
-    //应用数据仅有可能出现在应用地址空间低于base_size字节的区域中。
-    //借助它我们可以清楚的知道应用有多少数据驻留在内存中
-    pub base_size: usize,
-    pub task_cx: TaskContext,//将暂停的任务的任务上下文保存在任务控制块中
-    pub task_status: TaskStatus,//当前进程的执行状态
-    pub memory_set: MemorySet,//应用地址空间
-    pub parent: Option<Weak<TaskControlBlock>>,//指向当前进程的父进程
-    //将当前进程的所有子进程的任务控制块以Arc智能指针的形式保存在一个向量中
-    pub children: Vec<Arc<TaskControlBlock>>,
-    //当进程调用 exit 系统调用主动退出或者执行出错由内核终止的时候，它的退出码exit_code
-    //会被内核保存在它的任务控制块中，并等待它的父进程通过 waitpid 回收它的资源的同时也收集
-    //它的 PID 以及退出码
-    pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub task_cx: TaskContext,    //将暂停的线程的任务上下文保存在线程控制块中
+    pub task_status: TaskStatus, //当前线程的执行状态
+    pub exit_code: Option<i32>,  //线程退出之后由 waittid 取走的退出码
+    pub signal_mask: SignalFlags, //被屏蔽的信号，它们会一直等待到解除屏蔽之后再处理
+    pub nice: i32,               //线程的 nice 值，越小优先级越高
+    #[cfg(feature = "sched_stride")]
+    pub pass: u64, //步长调度中线程的行程值
 }
 
 impl TaskControlBlockInner {
     pub fn get_trap_cx(&self) -> &'static mut TrapContext {
         self.trap_cx_ppn.get_mut()
     }
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
-    }
-    fn get_status(&self) -> TaskStatus {
-        self.task_status
-    }
-    pub fn is_zombie(&self) -> bool {
-        self.get_status() == TaskStatus::Zombie
-    }
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
-        } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
-        }
-    }
 }
 
-//在内核中手动生成的进程只有初始进程initproc，余下所有的进程都是它直接或间接fork出来的。
-//当一个子进程被fork出来之后，它可以调用exec系统调用来加载并执行另一个可执行文件。
 impl TaskControlBlock {
     pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
         self.inner.exclusive_access()
     }
-    //创建一个新的进程，目前仅用于内核中手动创建唯一一个初始进程 initproc
-    pub fn new(elf_data: &[u8]) -> Self {
-		//解析应用的 ELF 执行文件得到应用地址空间 memory_set ，用户栈在应用地址空间中的位置 user_sp 以及应用的入口点 entry_point
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-		//查页表找到位于应用地址空间中新创建的Trap 上下文被实际放在哪个物理页帧上，用来做后续的初始化
-        let trap_cx_ppn = memory_set
-			.translate(VirtAddr::from(TRAP_CONTEXT).into())
-			.unwrap()
-			.ppn();
-		//为该进程分配PID以及内核栈，并记录下内核栈在内核地址空间的位置kernel_stack_top
-        let pid_handle = pid_alloc();
-	    let kernel_stack = KernelStack::new(&pid_handle);
+    //使用已经分配好的资源 res 创建进程中的一个线程，并为它分配内核栈。
+    //在内核栈上压入初始化的任务上下文，使得第一次任务切换到它的时候可以跳转到trap_return并进入用户态开始执行
+    pub fn new(
+        process: &Arc<ProcessControlBlock>,
+        res: TaskUserRes,
+        signal_mask: SignalFlags,
+        nice: i32,
+    ) -> Self {
+        let trap_cx_ppn = res.trap_cx_ppn(&process.inner_exclusive_access().memory_set);
+        let kernel_stack = KernelStack::new();
         let kernel_stack_top = kernel_stack.get_top();
-		//在该进程的内核栈上压入初始化的任务上下文，使得第一次任务切换到它的时候可以跳转到trap_return并进入用户态开始执行
-        //let task_cx_ptr = kernel_stack.push_on_top(TaskContext::goto_trap_return());
-		//整合之前的部分信息创建进程控制块
-        let task_control_block = Self {
-            pid: pid_handle,
+        Self {
+            process: Arc::downgrade(process),
             kernel_stack,
             inner: unsafe {
                 UPSafeCell::new(TaskControlBlockInner {
+                    res: Some(res),
                     trap_cx_ppn,
-                    base_size: user_sp,
                     task_cx: TaskContext::goto_trap_return(kernel_stack_top),
                     task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: vec![
-                        // 0 -> stdin
-                        Some(Arc::new(Stdin)),
-                        // 1 -> stdout
-                        Some(Arc::new(Stdout)),
-                        // 2 -> stderr
-                        Some(Arc::new(Stdout)),
-                    ],
+                    exit_code: None,
+                    signal_mask,
+                    nice,
+                    #[cfg(feature = "sched_stride")]
+                    pass: 0,
                 })
             },
-        };
-            
-		//task_control_block.acquire_inner_lock().init_rlimits();
-		let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-		//初始化位于该进程应用地址空间中的 Trap 上下文，使得第一次进入用户态的时候
-        //能正确跳转到应用入口点并设置好用户栈，同时也保证在 Trap 的时候用户态能正确进入内核态。
-        *trap_cx = TrapContext::app_init_context(
-			entry_point,
-			user_sp,
-			KERNEL_SPACE.exclusive_access().token(),
-			kernel_stack_top,
-			trap_handler as usize,
-		);
-		task_control_block
-	}
-
-    //当前进程 fork 出来一个与之几乎相同的子进程
-    pub fn fork(self: &Arc<TaskControlBlock>) -> Arc<TaskControlBlock> {
-		// ---- access parent PCB exclusively
-        let mut parent_inner = self.inner_exclusive_access();
-        // copy user space(include trap context)
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // copy fd table
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
-        for fd in parent_inner.fd_table.iter() {
-            if let Some(file) = fd {
-                new_fd_table.push(Some(file.clone()));
-            } else {
-                new_fd_table.push(None);
-            }
         }
-        let task_control_block = Arc::new(TaskControlBlock {
-            pid: pid_handle,
-            kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: parent_inner.base_size,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: Some(Arc::downgrade(self)),
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: new_fd_table,
-                })
-            },
-        });
-        // add child
-        parent_inner.children.push(task_control_block.clone());
-        // modify kernel_sp in trap_cx
-        // **** access children PCB exclusively
-        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-        trap_cx.kernel_sp = kernel_stack_top;
-        // return
-        task_control_block
-        // ---- release parent PCB automatically
-        // **** release children PCB automatically
-	}
-
-    pub fn exec(&self, elf_data: &[u8]) {
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-
-        // **** access inner exclusively
-        let mut inner = self.inner_exclusive_access();
-        // substitute memory_set
-        inner.memory_set = memory_set;
-        // update trap_cx ppn
-        inner.trap_cx_ppn = trap_cx_ppn;
-        // initialize trap_cx
-        let trap_cx = inner.get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            self.kernel_stack.get_top(),
-            trap_handler as usize,
-        );
-        // **** release inner automatically
     }
-
-    //以usize的形式返回当前进程的进程标识符
-    pub fn getpid(&self) -> usize {
-        self.pid.0
+    //线程所在的进程
+    pub fn process(&self) -> Arc<ProcessControlBlock> {
+        self.process.upgrade().unwrap()
+    }
+    pub fn get_user_token(&self) -> usize {
+        self.process().inner_exclusive_access().get_user_token()
     }
 }
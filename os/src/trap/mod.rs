@@ -1,10 +1,11 @@
 mod context;
 
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::{TRAMPOLINE, USER_SPACE_TOP};
 use crate::syscall::syscall;
+use crate::memory::{MapPermission, VirtAddr};
 use crate::task::{
-    current_trap_cx, current_user_token, 
-    exit_current_and_run_next, 
+    current_process, current_trap_cx, current_trap_cx_user_va, current_user_token,
+    force_signal, handle_signals, SignalFlags,
     suspend_current_and_run_next
 };
 use crate::timer::{set_next_trigger};
@@ -68,7 +69,10 @@ pub fn trap_handler() -> ! {
             //enable_supervisor_interrupt();
 
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            let result = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            );
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             //父进程系统调用的返回值会在 trap_handler 中 syscall 返回之后再设置
@@ -76,6 +80,11 @@ pub fn trap_handler() -> ! {
             //返回值为子进程的 PID ，而子进程的返回值则为 0 。通过返回值是否为 0 可以区分父子进程
             cx.x[10] = result as usize;
         }
+        //访问按需分配的页面或者写入写时复制的页面：分配或复制出物理页帧之后，回到用户态重新执行这条指令
+        Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::InstructionPageFault)
+            if handle_page_fault(scause.cause(), stval) => {}
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionFault)
@@ -83,18 +92,17 @@ pub fn trap_handler() -> ! {
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
             println!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
+                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, send SIGSEGV to it.",
                 scause.cause(),
                 stval,
                 current_trap_cx().sepc,
             );
-            // page fault exit code
-            exit_current_and_run_next(-2);
+            //非法访存变成 SIGSEGV ，由用户的处理函数处理或者按照默认动作终止进程
+            force_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            // illegal instruction exit code
-            exit_current_and_run_next(-3);
+            println!("[kernel] IllegalInstruction in application, send SIGILL to it.");
+            force_signal(SignalFlags::SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
@@ -108,14 +116,33 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    //回到用户态之前处理收到的信号
+    handle_signals();
     trap_return();
 }
 
+//按照页错误的类型得到这次访问需要的权限，交给当前进程的地址空间处理，返回 false 说明这是一次非法访问
+fn handle_page_fault(cause: Trap, stval: usize) -> bool {
+    if stval >= USER_SPACE_TOP {
+        return false;
+    }
+    let access = match cause {
+        Trap::Exception(Exception::LoadPageFault) => MapPermission::R,
+        Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+        _ => MapPermission::X,
+    };
+    current_process()
+        .inner_exclusive_access()
+        .memory_set
+        .handle_page_fault(VirtAddr::from(stval).floor(), access)
+}
+
 #[no_mangle]
 pub fn trap_return() -> ! {
     //disable_supervisor_interrupt();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    //每个线程的 Trap 上下文在用户地址空间中的位置各不相同
+    let trap_cx_ptr = current_trap_cx_user_va();
     let user_satp = current_user_token();
     extern "C" {
         fn __alltraps();
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{execve, fork, waitpid};
+
+const ARGS: [&str; 3] = ["argtest\0", "hello\0", "world\0"];
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc == ARGS.len() {
+        // 被 exec 的子进程：检查内核放在用户栈上的参数
+        for (arg, expected) in argv.iter().zip(ARGS.iter()) {
+            assert_eq!(*arg, expected.trim_end_matches('\0'));
+        }
+        return 0;
+    }
+    let pid = fork();
+    if pid == 0 {
+        let args = [
+            ARGS[0].as_ptr(),
+            ARGS[1].as_ptr(),
+            ARGS[2].as_ptr(),
+            core::ptr::null::<u8>(),
+        ];
+        let envs = ["PATH=/\0".as_ptr(), core::ptr::null::<u8>()];
+        execve(ARGS[0], &args, &envs);
+        unreachable!();
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("argtest passed!");
+    0
+}
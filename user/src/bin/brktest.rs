@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk};
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let heap_bottom = sbrk(0);
+    assert!(heap_bottom > 0);
+    // 堆向上增长，新的页面可以读写
+    assert_eq!(sbrk((3 * PAGE_SIZE) as isize), heap_bottom);
+    assert_eq!(sbrk(0), heap_bottom + (3 * PAGE_SIZE) as isize);
+    let heap = unsafe {
+        core::slice::from_raw_parts_mut(heap_bottom as usize as *mut u8, 3 * PAGE_SIZE)
+    };
+    heap.fill(0x5a);
+    assert!(heap.iter().all(|b| *b == 0x5a));
+    // 不按页对齐的程序断点
+    assert_eq!(sbrk(10), heap_bottom + (3 * PAGE_SIZE) as isize);
+    assert_eq!(sbrk(-10 - PAGE_SIZE as isize), heap_bottom + (3 * PAGE_SIZE) as isize + 10);
+    assert_eq!(sbrk(0), heap_bottom + (2 * PAGE_SIZE) as isize);
+    // 程序断点不能低于堆底
+    assert_eq!(brk(1), heap_bottom + (2 * PAGE_SIZE) as isize);
+    assert_eq!(sbrk(-(3 * PAGE_SIZE as isize)), -1);
+    assert_eq!(sbrk(-(2 * PAGE_SIZE as isize)), heap_bottom + (2 * PAGE_SIZE) as isize);
+    assert_eq!(sbrk(0), heap_bottom);
+
+    // 超过初始 32 KiB 堆空间的分配由分配器通过 sbrk 扩展堆来满足
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..16 * 1024 {
+        v.push(i);
+    }
+    assert!(v.iter().enumerate().all(|(i, x)| i == *x));
+    assert!(sbrk(0) > heap_bottom);
+    println!("brktest passed!");
+    0
+}
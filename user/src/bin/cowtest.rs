@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, waitpid, write};
+
+const LEN: usize = 4096 * 4;
+static mut DATA: [u8; LEN] = [0; LEN];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let data = unsafe { &mut DATA };
+    data.fill(1);
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        // fork 之后子进程先看到与父进程相同的内容
+        assert!(data.iter().all(|b| *b == 1));
+        // 子进程自己写入，触发写时复制
+        data[LEN / 2..].fill(2);
+        assert!(data[..LEN / 2].iter().all(|b| *b == 1));
+        assert!(data[LEN / 2..].iter().all(|b| *b == 2));
+        // 内核通过 read 写入共享页面时同样只能修改子进程自己的副本
+        assert_eq!(read(pipe_fd[0], &mut data[..16]), 16);
+        assert!(data[..16].iter().all(|b| *b == 3));
+        close(pipe_fd[0]);
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    assert_eq!(write(pipe_fd[1], &[3u8; 16]), 16);
+    close(pipe_fd[1]);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // 子进程的修改对父进程不可见
+    assert!(data.iter().all(|b| *b == 1));
+    println!("cowtest passed!");
+    0
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, exit, fork, getcwd, mkdir, open, read, rmdir, unlink, waitpid, write, yield_,
+    OpenFlags,
+};
+
+fn cwd(buf: &mut [u8]) -> &str {
+    let len = getcwd(buf);
+    assert!(len > 0);
+    core::str::from_utf8(&buf[..len as usize]).unwrap()
+}
+
+// 父子进程之间通过创建文件来通知对方
+fn notify(path: &str) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+}
+
+fn wait_for(path: &str) {
+    loop {
+        let fd = open(path, OpenFlags::RDONLY);
+        if fd > 0 {
+            close(fd as usize);
+            return;
+        }
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, directories!";
+    let mut path_buf = [0u8; 64];
+    assert_eq!(mkdir("/dirtest\0"), 0);
+    assert_eq!(mkdir("/dirtest/a\0"), 0);
+    // 当前工作目录是根目录，相对路径从这里开始查找
+    assert_eq!(mkdir("dirtest/a/b\0"), 0);
+    // 目录已经存在或者父目录不存在时创建失败
+    assert_eq!(mkdir("/dirtest/a\0"), -1);
+    assert_eq!(mkdir("/dirtest/x/y\0"), -1);
+
+    let fd = open("/dirtest/a/b/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    assert_eq!(chdir("/dirtest/a\0"), 0);
+    assert_eq!(cwd(&mut path_buf), "/dirtest/a");
+    let fd = open("./b/../b/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 100];
+    let read_len = read(fd as usize, &mut buffer) as usize;
+    close(fd as usize);
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..read_len]).unwrap());
+
+    // 普通文件不是目录，不能切换过去
+    assert_eq!(chdir("b/file\0"), -1);
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(cwd(&mut path_buf), "/dirtest");
+    assert_eq!(chdir("/..\0"), 0);
+    assert_eq!(cwd(&mut path_buf), "/");
+
+    // a non-empty directory cannot be removed, nor can a file be removed as a directory
+    assert_eq!(rmdir("/dirtest/a/b\0"), -1);
+    assert_eq!(rmdir("/dirtest/a/b/file\0"), -1);
+    assert_eq!(unlink("/dirtest/a/b\0"), -1);
+    assert_eq!(unlink("/dirtest/a/b/file\0"), 0);
+    assert_eq!(open("/dirtest/a/b/file\0", OpenFlags::RDONLY), -1);
+    // 其它进程的当前工作目录也不能被删除
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(chdir("/dirtest/a/b\0"), 0);
+        notify("/dirtest/ready\0");
+        wait_for("/dirtest/done\0");
+        assert_eq!(cwd(&mut path_buf), "/dirtest/a/b");
+        exit(0);
+    }
+    wait_for("/dirtest/ready\0");
+    assert_eq!(rmdir("/dirtest/a/b\0"), -1);
+    notify("/dirtest/done\0");
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(unlink("/dirtest/ready\0"), 0);
+    assert_eq!(unlink("/dirtest/done\0"), 0);
+    assert_eq!(rmdir("/dirtest/a/b\0"), 0);
+    assert_eq!(rmdir("/dirtest/a\0"), 0);
+    assert_eq!(rmdir("dirtest\0"), 0);
+    assert_eq!(chdir("/dirtest\0"), -1);
+    println!("dirtest passed!");
+    0
+}
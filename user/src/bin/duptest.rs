@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, exec, fork, open, read, unlink, waitpid, write, OpenFlags};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let path = "duptest_out\0";
+    // dup 分配最小的空闲文件描述符，两个文件描述符共享同一个读写位置
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let copy = dup(fd);
+    assert_eq!(copy, fd as isize + 1);
+    write(fd, b"Hello, ");
+    write(copy as usize, b"dup!");
+    close(fd);
+    close(copy as usize);
+    let mut buffer = [0u8; 64];
+    let fd = open(path, OpenFlags::RDONLY) as usize;
+    let len = read(fd, &mut buffer) as usize;
+    assert_eq!(&buffer[..len], b"Hello, dup!");
+    close(fd);
+
+    // 无效的文件描述符不能被复制
+    assert_eq!(dup(100), -1);
+    assert_eq!(dup2(100, 5), -1);
+    assert_eq!(dup2(1, 1), 1);
+    assert_eq!(dup2(100, 100), -1);
+
+    // 子进程将标准输出重定向到文件之后再 exec ，新程序的输出就写入了文件
+    let pid = fork();
+    if pid == 0 {
+        let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd > 0);
+        assert_eq!(dup2(fd as usize, 1), 1);
+        close(fd as usize);
+        exec("hello_world\0", &["hello_world\0".as_ptr(), core::ptr::null::<u8>()]);
+        unreachable!();
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    let fd = open(path, OpenFlags::RDONLY) as usize;
+    let len = read(fd, &mut buffer) as usize;
+    close(fd);
+    let output = core::str::from_utf8(&buffer[..len]).unwrap();
+    assert!(output.starts_with("pid "));
+    assert!(output.ends_with(": Hello world from user mode program!\n"));
+    assert_eq!(unlink(path), 0);
+    println!("duptest passed!");
+    0
+}
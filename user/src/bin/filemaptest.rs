@@ -0,0 +1,100 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, mmap_file, msync, munmap, open, pread, pwrite, unlink, waitpid, OpenFlags,
+    MAP_PRIVATE, MAP_SHARED, PROT_READ, PROT_WRITE,
+};
+
+const PAGE_SIZE: usize = 4096;
+// 文件最后一页只有一部分在文件之内
+const FILE_LEN: usize = 2 * PAGE_SIZE + 100;
+const MAP_LEN: usize = 3 * PAGE_SIZE;
+
+fn mapping(start: isize) -> &'static mut [u8] {
+    assert!(start > 0);
+    unsafe { core::slice::from_raw_parts_mut(start as usize as *mut u8, MAP_LEN) }
+}
+
+fn read_byte(fd: usize, offset: usize) -> u8 {
+    let mut byte = [0u8];
+    assert_eq!(pread(fd, &mut byte, offset), 1);
+    byte[0]
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let path = "filemaptest_file\0";
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut content = [0u8; FILE_LEN];
+    for (i, byte) in content.iter_mut().enumerate() {
+        *byte = (i % 251) as u8;
+    }
+    assert_eq!(pwrite(fd, &content, 0), FILE_LEN as isize);
+
+    // 共享映射的页面从文件中读入，超出文件末尾的部分为 0
+    let shared = mapping(mmap_file(0, MAP_LEN, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0));
+    assert_eq!(&shared[..FILE_LEN], &content[..]);
+    assert!(shared[FILE_LEN..].iter().all(|b| *b == 0));
+
+    // 通过映射的修改和通过 write 的修改立即互相可见
+    shared[10] = 0xaa;
+    assert_eq!(read_byte(fd, 10), 0xaa);
+    assert_eq!(pwrite(fd, &[0xbb], PAGE_SIZE + 1), 1);
+    assert_eq!(shared[PAGE_SIZE + 1], 0xbb);
+
+    // 私有映射中的修改对文件和其它映射都不可见
+    let private = mapping(mmap_file(0, MAP_LEN, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0));
+    assert_eq!(private[10], 0xaa);
+    private[10] = 0xcc;
+    assert_eq!(shared[10], 0xaa);
+    assert_eq!(read_byte(fd, 10), 0xaa);
+
+    // 从文件的第二页开始映射
+    let second = mmap_file(0, PAGE_SIZE, PROT_READ, MAP_PRIVATE, fd, PAGE_SIZE);
+    assert!(second > 0);
+    assert_eq!(unsafe { *((second as usize + 1) as *const u8) }, 0xbb);
+
+    // fork 之后父子进程仍然共享同一个共享映射
+    let pid = fork();
+    if pid == 0 {
+        shared[20] = 0xdd;
+        private[20] = 0xee;
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(shared[20], 0xdd);
+    assert_eq!(private[20], content[20]);
+
+    // 超出文件末尾的修改不会改变文件的大小
+    shared[FILE_LEN] = 0xff;
+    assert_eq!(msync(shared.as_ptr() as usize, MAP_LEN), 0);
+    let mut byte = [0u8];
+    assert_eq!(pread(fd, &mut byte, FILE_LEN), 0);
+    assert_eq!(munmap(shared.as_ptr() as usize, MAP_LEN), 0);
+    assert_eq!(munmap(private.as_ptr() as usize, MAP_LEN), 0);
+    assert_eq!(munmap(second as usize, PAGE_SIZE), 0);
+    close(fd);
+
+    // 页缓存被释放之后重新打开文件，之前的修改已经写回磁盘
+    let fd = open(path, OpenFlags::RDONLY) as usize;
+    assert_eq!(read_byte(fd, 10), 0xaa);
+    assert_eq!(read_byte(fd, 20), 0xdd);
+    assert_eq!(read_byte(fd, PAGE_SIZE + 1), 0xbb);
+    assert_eq!(read_byte(fd, 30), content[30]);
+
+    // 只读打开的文件不能建立可写的共享映射
+    assert_eq!(mmap_file(0, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0), -1);
+    assert!(mmap_file(0, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0) > 0);
+    close(fd);
+    assert_eq!(unlink(path), 0);
+    println!("filemaptest passed!");
+    0
+}
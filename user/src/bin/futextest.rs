@@ -0,0 +1,120 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::AtomicU32;
+use user_lib::{
+    exit, fork, futex_wait, futex_wake, kill, shmat, shmctl, shmget, sleep, thread_create, waitpid,
+    waittid, yield_, FutexCondvar, FutexMutex, IPC_PRIVATE, IPC_RMID, SIGKILL,
+};
+
+const THREAD_COUNT: usize = 4;
+const ROUNDS: usize = 200;
+
+static COUNTER: FutexMutex<usize> = FutexMutex::new(0);
+static READY: FutexMutex<bool> = FutexMutex::new(false);
+static READY_COND: FutexCondvar = FutexCondvar::new();
+static WORD: AtomicU32 = AtomicU32::new(0);
+
+// 放在共享内存中由父子进程共同使用，全为 0 的内存就是初始状态
+struct Shared {
+    counter: FutexMutex<usize>,
+}
+
+// 在临界区中让出处理器，制造锁上的竞争
+fn add_rounds(counter: &FutexMutex<usize>) {
+    for _ in 0..ROUNDS {
+        let mut counter = counter.lock();
+        let value = *counter;
+        yield_();
+        *counter = value + 1;
+    }
+}
+
+extern "C" fn adder(_arg: usize) -> ! {
+    add_rounds(&COUNTER);
+    exit(0)
+}
+
+extern "C" fn notifier(_arg: usize) -> ! {
+    sleep(10);
+    *READY.lock() = true;
+    READY_COND.notify_all();
+    exit(0)
+}
+
+fn wait_thread(tid: isize) {
+    assert!(tid > 0);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 值不相等时不会睡眠，没有等待者时唤醒不到任何线程
+    assert_eq!(futex_wait(&WORD, 1), -1);
+    assert_eq!(futex_wake(&WORD, 1), 0);
+
+    // 没有竞争时加锁和解锁都不进入内核
+    assert!(COUNTER.try_lock().is_some());
+    let guard = COUNTER.lock();
+    assert!(COUNTER.try_lock().is_none());
+    drop(guard);
+
+    // 线程之间的锁
+    let mut tids = [0isize; THREAD_COUNT];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, 0);
+    }
+    for tid in tids {
+        wait_thread(tid);
+    }
+    assert_eq!(*COUNTER.lock(), THREAD_COUNT * ROUNDS);
+
+    // 在条件变量上等待另一个线程设置标志
+    let tid = thread_create(notifier as usize, 0);
+    let mut ready = READY.lock();
+    while !*ready {
+        ready = READY_COND.wait(ready);
+    }
+    drop(ready);
+    wait_thread(tid);
+
+    // 放在共享内存中的锁，父子进程映射到同一个物理地址
+    let shmid = shmget(IPC_PRIVATE, core::mem::size_of::<Shared>(), 0);
+    assert!(shmid >= 0);
+    let shmid = shmid as usize;
+    let addr = shmat(shmid, 0, 0);
+    assert!(addr > 0);
+    let shared = unsafe { &*(addr as usize as *const Shared) };
+    let pid = fork();
+    if pid == 0 {
+        add_rounds(&shared.counter);
+        exit(0);
+    }
+    add_rounds(&shared.counter);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(*shared.counter.lock(), 2 * ROUNDS);
+    assert_eq!(shmctl(shmid, IPC_RMID), 0);
+
+    // fork 之后各自的私有变量在不同的物理地址上，父进程唤醒不了在自己那份副本上等待的子进程
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            futex_wait(&WORD, 0);
+        }
+    }
+    sleep(10);
+    assert_eq!(futex_wake(&WORD, 1), 0);
+    // 在 futex 上睡眠的进程仍然可以被 SIGKILL 杀死
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    println!("futextest passed!");
+    0
+}
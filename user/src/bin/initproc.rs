@@ -11,7 +11,7 @@ fn main() -> i32 {
     //fork返回值为0的分支，表示子进程，此行直接通过exec执行shell程序user_shell，
     //注意我们需要在字符串末尾手动加入\0 ，因为Rust在将这些字符串连接到只读数据段的时候不会插入\0
     if fork() == 0 {
-        exec("user_shell\0");
+        exec("user_shell\0", &["user_shell\0".as_ptr(), core::ptr::null::<u8>()]);
     } else {
         //返回值不为 0 的分支，表示调用 fork 的用户初始程序 initproc 自身。
         loop {
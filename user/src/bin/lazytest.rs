@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mmap, munmap, pipe, read, write, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+// 比物理内存还大的 .bss ，只有被访问过的页面才会分配物理页帧
+const BSS_LEN: usize = 16 * 1024 * 1024;
+static mut BSS: [u8; BSS_LEN] = [0; BSS_LEN];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let bss = unsafe { &mut BSS };
+    for i in (0..BSS_LEN).step_by(1024 * 1024) {
+        assert_eq!(bss[i], 0);
+        bss[i] = 1;
+    }
+    assert_eq!(bss[BSS_LEN - 1], 0);
+
+    // 同样远大于物理内存的匿名映射
+    let len = 64 * 1024 * 1024;
+    let start = mmap(0, len, PROT_READ | PROT_WRITE);
+    assert!(start > 0);
+    let buffer = unsafe { core::slice::from_raw_parts_mut(start as usize as *mut u8, len) };
+    for i in (0..len).step_by(4 * 1024 * 1024) {
+        assert_eq!(buffer[i], 0);
+        buffer[i] = (i / PAGE_SIZE) as u8;
+    }
+
+    // 内核读写用户还没有访问过的页面时同样按需分配
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let untouched = &buffer[PAGE_SIZE..PAGE_SIZE + 16];
+    assert_eq!(write(pipe_fd[1], untouched), 16);
+    let untouched = &mut buffer[3 * PAGE_SIZE..3 * PAGE_SIZE + 16];
+    assert_eq!(read(pipe_fd[0], untouched), 16);
+    assert!(untouched.iter().all(|b| *b == 0));
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // 部分页面从未被访问过的区域也可以正常解除映射
+    assert_eq!(munmap(start as usize, len), 0);
+    println!("lazytest passed!");
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, link, mkdir, open, read, rmdir, unlink, write, OpenFlags};
+
+fn read_all(path: &str, buffer: &mut [u8]) -> usize {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let len = read(fd as usize, buffer);
+    close(fd as usize);
+    len as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, hard links!";
+    let mut buffer = [0u8; 64];
+    assert_eq!(mkdir("/linktest\0"), 0);
+    let fd = open("/linktest/origin\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    assert_eq!(link("/linktest/origin\0", "/linktest/alias\0"), 0);
+    assert_eq!(link("/linktest/origin\0", "linktest_alias\0"), 0);
+    // 新的名字已经存在、目录以及不存在的文件都不能建立链接
+    assert_eq!(link("/linktest/origin\0", "/linktest/alias\0"), -1);
+    assert_eq!(link("/linktest\0", "/linktest_dir\0"), -1);
+    assert_eq!(link("/linktest/none\0", "/linktest/none2\0"), -1);
+
+    // 通过任何一个名字写入的内容都能从其他名字读到
+    let fd = open("linktest_alias\0", OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"J");
+    close(fd as usize);
+    let len = read_all("/linktest/alias\0", &mut buffer);
+    assert_eq!(&buffer[..len], b"Jello, hard links!");
+
+    // 删除原来的名字后，其余的名字仍然有效
+    assert_eq!(unlink("/linktest/origin\0"), 0);
+    assert_eq!(open("/linktest/origin\0", OpenFlags::RDONLY), -1);
+    let len = read_all("linktest_alias\0", &mut buffer);
+    assert_eq!(&buffer[..len], b"Jello, hard links!");
+
+    // 文件的最后一个名字被删除后，已经打开它的进程仍然可以继续读写
+    let fd = open("/linktest/alias\0", OpenFlags::RDWR);
+    assert!(fd > 0);
+    assert_eq!(unlink("/linktest/alias\0"), 0);
+    assert_eq!(unlink("linktest_alias\0"), 0);
+    let len = read(fd as usize, &mut buffer) as usize;
+    assert_eq!(&buffer[..len], b"Jello, hard links!");
+    close(fd as usize);
+
+    assert_eq!(rmdir("/linktest\0"), 0);
+    println!("linktest passed!");
+    0
+}
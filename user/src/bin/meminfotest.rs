@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mmap, munmap, pipe, sysinfo, SysInfo, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 256;
+// 创建管道直到内核堆超出初始大小，最多创建这么多个
+const MAX_PIPES: usize = 10000;
+
+fn info() -> SysInfo {
+    let mut info = SysInfo::default();
+    assert_eq!(sysinfo(&mut info), 0);
+    assert!(info.free_frames <= info.total_frames);
+    assert!(info.heap_used <= info.heap_total);
+    info
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let before = info();
+    println!("{:?}", before);
+
+    // 访问过的页面占用物理页帧，解除映射之后归还（页表本身占用的物理页帧除外）
+    let start = mmap(0, PAGES * PAGE_SIZE, PROT_READ | PROT_WRITE) as usize;
+    for page in 0..PAGES {
+        unsafe {
+            *((start + page * PAGE_SIZE) as *mut u8) = 1;
+        }
+    }
+    let touched = info();
+    assert!(touched.free_frames + PAGES <= before.free_frames);
+    assert_eq!(munmap(start, PAGES * PAGE_SIZE), 0);
+    assert!(info().free_frames + 16 >= before.free_frames);
+
+    // 大量内核对象让内核堆从物理页帧分配器中扩展
+    let mut last_fd = 0;
+    let mut grown = info();
+    for _ in 0..MAX_PIPES {
+        let mut pipe_fd = [0usize; 2];
+        assert_eq!(pipe(&mut pipe_fd), 0);
+        last_fd = pipe_fd[1];
+        grown = info();
+        if grown.heap_total > before.heap_total {
+            break;
+        }
+    }
+    println!("{:?}", grown);
+    assert!(grown.heap_total > before.heap_total);
+    assert!(grown.heap_used > before.heap_used);
+    // 管道的文件描述符从 3 开始连续分配
+    for fd in 3..=last_fd {
+        close(fd);
+    }
+    assert!(info().heap_used < grown.heap_used);
+    println!("meminfotest passed!");
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid, PROT_READ, PROT_WRITE, SIGSEGV};
+
+const PAGE_SIZE: usize = 4096;
+
+fn as_slice(start: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(start as *mut u8, len) }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 比用户堆大得多的缓冲区
+    let len = 16 * PAGE_SIZE;
+    let start = mmap(0, len, PROT_READ | PROT_WRITE);
+    assert!(start > 0);
+    let start = start as usize;
+    assert_eq!(start % PAGE_SIZE, 0);
+    let buffer = as_slice(start, len);
+    assert!(buffer.iter().all(|b| *b == 0));
+    for (i, b) in buffer.iter_mut().enumerate() {
+        *b = (i / PAGE_SIZE) as u8;
+    }
+
+    // 不合法的参数
+    assert_eq!(mmap(0, 0, PROT_READ), -1);
+    assert_eq!(mmap(start + 1, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(mmap(0, PAGE_SIZE, 0), -1);
+    // 指定的地址与已有的映射重叠
+    assert_eq!(mmap(start + PAGE_SIZE, PAGE_SIZE, PROT_READ), -1);
+    // 紧接在后面的区域是空闲的
+    let next = start + len;
+    assert_eq!(mmap(next, PAGE_SIZE, PROT_READ), next as isize);
+    assert_eq!(munmap(next, PAGE_SIZE), 0);
+
+    // 从中间挖掉两页，原来的区域分裂成前后两段，其余页面的内容不变
+    assert_eq!(munmap(start + 4 * PAGE_SIZE, 2 * PAGE_SIZE), 0);
+    for page in (0..4).chain(6..16) {
+        let offset = page * PAGE_SIZE;
+        assert!(buffer[offset..offset + PAGE_SIZE].iter().all(|b| *b == page as u8));
+    }
+    // 空出来的两页可以重新映射，得到的是全零的新页面
+    assert_eq!(
+        mmap(start + 4 * PAGE_SIZE, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE),
+        (start + 4 * PAGE_SIZE) as isize
+    );
+    assert!(buffer[4 * PAGE_SIZE..6 * PAGE_SIZE].iter().all(|b| *b == 0));
+
+    // 映射的内存在 fork 之后同样写时复制
+    let pid = fork();
+    if pid == 0 {
+        buffer[0] = 100;
+        exit(buffer[PAGE_SIZE] as i32);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 1);
+    assert_eq!(buffer[0], 0);
+
+    // 一次解除跨越多个逻辑段的映射
+    assert_eq!(munmap(start, len), 0);
+    assert_eq!(munmap(start, PAGE_SIZE), 0);
+    // 访问已经解除映射的页面会被 SIGSEGV 杀死
+    let pid = fork();
+    if pid == 0 {
+        buffer[0] = 1;
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGSEGV as i32));
+    println!("mmaptest passed!");
+    0
+}
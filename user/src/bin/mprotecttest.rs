@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{
+    close, exit, fork, mmap, mprotect, munmap, pipe, read, waitpid, write, PROT_EXEC, PROT_READ,
+    PROT_WRITE, SIGSEGV,
+};
+
+const PAGE_SIZE: usize = 4096;
+const LEN: usize = 4 * PAGE_SIZE;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = mmap(0, LEN, PROT_READ | PROT_WRITE);
+    assert!(start > 0);
+    let start = start as usize;
+    let buffer = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, LEN) };
+    buffer.fill(1);
+
+    // 只修改中间两个页面的权限，逻辑段被分成三段
+    let middle = start + PAGE_SIZE;
+    assert_eq!(mprotect(middle, 2 * PAGE_SIZE, PROT_READ), 0);
+    assert_eq!(buffer[PAGE_SIZE], 1);
+    buffer[0] = 2;
+    buffer[3 * PAGE_SIZE] = 2;
+
+    // 写入只读页面的进程被 SIGSEGV 杀死
+    let pid = fork();
+    if pid == 0 {
+        buffer[PAGE_SIZE] = 3;
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGSEGV as i32));
+
+    // 内核也不能代替用户写入只读页面
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(write(pipe_fd[1], &buffer[..16]), 16);
+    let readonly = unsafe { core::slice::from_raw_parts_mut(middle as *mut u8, 16) };
+    assert_eq!(read(pipe_fd[0], readonly), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // 恢复写权限之后相邻的逻辑段重新合并，可以一次解除映射
+    assert_eq!(mprotect(middle, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    buffer[PAGE_SIZE] = 4;
+    assert_eq!(buffer[PAGE_SIZE], 4);
+
+    // 像 JIT 一样先写入指令，再将页面从 RW 切换到 RX 后执行：li a0, 42; ret
+    let code = unsafe { core::slice::from_raw_parts_mut(start as *mut u32, 2) };
+    code[0] = 0x02a0_0513;
+    code[1] = 0x0000_8067;
+    assert_eq!(mprotect(start, PAGE_SIZE, PROT_READ | PROT_EXEC), 0);
+    let func: extern "C" fn() -> usize = unsafe { core::mem::transmute(start) };
+    unsafe {
+        asm!("fence.i");
+    }
+    assert_eq!(func(), 42);
+
+    // 区间中有没有映射的页面或者 start 没有按页对齐时失败
+    assert_eq!(mprotect(start + LEN - PAGE_SIZE, 2 * PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(mprotect(start + 1, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(munmap(start, LEN), 0);
+    assert_eq!(mprotect(start, PAGE_SIZE, PROT_READ), -1);
+    println!("mprotecttest passed!");
+    0
+}
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, fstat, kill, pipe, read, sleep, wait, waitpid, write, Stat, StatMode,
+    SIGKILL,
+};
+
+static STR: &str = "Hello, world!";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // create pipe
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    // read end
+    assert_eq!(pipe_fd[0], 3);
+    // write end
+    assert_eq!(pipe_fd[1], 4);
+    let mut st = Stat::new();
+    assert_eq!(fstat(pipe_fd[0], &mut st), 0);
+    assert_eq!(st.mode, StatMode::FIFO);
+    // 读端不能写，写端不能读
+    assert_eq!(write(pipe_fd[0], STR.as_bytes()), -1);
+    assert_eq!(read(pipe_fd[1], &mut [0u8; 1]), -1);
+    if fork() == 0 {
+        // child process, read from parent
+        // close write_end
+        close(pipe_fd[1]);
+        let mut buffer = [0u8; 32];
+        let len_read = read(pipe_fd[0], &mut buffer) as usize;
+        assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+        // 所有写端都关闭之后读到文件末尾
+        assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+        // close read_end
+        close(pipe_fd[0]);
+        println!("Read OK, child process exited!");
+        0
+    } else {
+        // parent process, write to child
+        // close read end
+        close(pipe_fd[0]);
+        assert_eq!(write(pipe_fd[1], STR.as_bytes()), STR.len() as isize);
+        // close write end
+        close(pipe_fd[1]);
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+
+        // 阻塞在管道上的进程可以被 SIGKILL 杀死，即使管道的写端一直没有关闭
+        assert_eq!(pipe(&mut pipe_fd), 0);
+        let pid = fork();
+        if pid == 0 {
+            read(pipe_fd[0], &mut [0u8; 1]);
+            exit(0);
+        }
+        sleep(10);
+        assert_eq!(kill(pid as usize, SIGKILL), 0);
+        assert_eq!(waitpid(pid as usize, &mut child_exit_code), pid);
+        assert_eq!(child_exit_code, -(SIGKILL as i32));
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        println!("pipetest passed!");
+        0
+    }
+}
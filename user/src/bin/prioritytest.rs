@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, getpriority, nice, setpriority, waitpid};
+
+const PERIOD_MS: isize = 200;
+
+// 在同一段时间内尽量多地循环，得到的循环次数与分到的处理器时间成正比
+fn spin(start: isize) -> i32 {
+    let mut count = 0;
+    while get_time() < start + PERIOD_MS {
+        count += 1;
+    }
+    count
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 设置和读取 nice 值，超出范围的值被截断
+    assert_eq!(getpriority(0), Some(0));
+    assert_eq!(setpriority(0, 5), 0);
+    assert_eq!(getpriority(0), Some(5));
+    assert_eq!(nice(-2), 0);
+    assert_eq!(getpriority(0), Some(3));
+    assert_eq!(setpriority(0, 100), 0);
+    assert_eq!(getpriority(0), Some(19));
+    assert_eq!(setpriority(0, -100), 0);
+    assert_eq!(getpriority(0), Some(-20));
+    assert_eq!(setpriority(100000, 0), -1);
+    assert_eq!(getpriority(100000), None);
+
+    // 子进程继承父进程的 nice 值，父进程可以修改子进程的 nice 值
+    assert_eq!(setpriority(0, 0), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(getpriority(0), Some(0));
+        while getpriority(0) != Some(7) {}
+        exit(0);
+    }
+    assert_eq!(setpriority(pid as usize, 7), 0);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 两个 nice 值不同的进程同时运行。步长调度下 nice 值小的进程循环次数多得多，先来先服务时两者差不多
+    let start = get_time();
+    let mut pids = [0isize; 2];
+    for (pid, nice) in pids.iter_mut().zip([-5, 5]) {
+        *pid = fork();
+        if *pid == 0 {
+            assert_eq!(setpriority(0, nice), 0);
+            exit(spin(start));
+        }
+    }
+    let mut counts = [0i32; 2];
+    for (pid, count) in pids.iter().zip(counts.iter_mut()) {
+        assert_eq!(waitpid(*pid as usize, count), *pid);
+    }
+    println!("nice -5: {} loops, nice 5: {} loops", counts[0], counts[1]);
+    println!("prioritytest passed!");
+    0
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, lseek, open, pread, pwrite, read, unlink, write, OpenFlags, SEEK_CUR, SEEK_END,
+    SEEK_SET,
+};
+
+const RECORD_SIZE: usize = 16;
+const RECORD_COUNT: usize = 64;
+// easy-fs 的文件最多有 27 个直接索引、128 个一级间接索引和 128 * 128 个二级间接索引的数据块
+const MAX_FILE_SIZE: usize = (27 + 128 + 128 * 128) * 512;
+
+fn record(i: usize) -> [u8; RECORD_SIZE] {
+    let mut record = [b'.'; RECORD_SIZE];
+    record[0] = b'0' + (i / 10) as u8;
+    record[1] = b'0' + (i % 10) as u8;
+    record
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let path = "seektest_data\0";
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    for i in 0..RECORD_COUNT {
+        assert_eq!(write(fd, &record(i)), RECORD_SIZE as isize);
+    }
+    let end = (RECORD_SIZE * RECORD_COUNT) as isize;
+    assert_eq!(lseek(fd, 0, SEEK_CUR), end);
+
+    // 按照任意顺序随机访问记录
+    let mut buffer = [0u8; RECORD_SIZE];
+    for i in [37, 2, 63, 0, 20] {
+        assert_eq!(lseek(fd, (i * RECORD_SIZE) as isize, SEEK_SET), (i * RECORD_SIZE) as isize);
+        assert_eq!(read(fd, &mut buffer), RECORD_SIZE as isize);
+        assert_eq!(buffer, record(i));
+    }
+    // 相对于当前位置和文件末尾移动
+    assert_eq!(lseek(fd, -(RECORD_SIZE as isize), SEEK_CUR), (20 * RECORD_SIZE) as isize);
+    assert_eq!(lseek(fd, -(RECORD_SIZE as isize), SEEK_END), end - RECORD_SIZE as isize);
+    assert_eq!(read(fd, &mut buffer), RECORD_SIZE as isize);
+    assert_eq!(buffer, record(RECORD_COUNT - 1));
+    assert_eq!(read(fd, &mut buffer), 0);
+    // 新的位置不能为负数，whence 也必须合法
+    assert_eq!(lseek(fd, -1, SEEK_SET), -1);
+    assert_eq!(lseek(fd, 0, 3), -1);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), end);
+
+    // pread/pwrite 不改变共享的读写位置
+    assert_eq!(lseek(fd, 5, SEEK_SET), 5);
+    assert_eq!(pwrite(fd, &record(99), 10 * RECORD_SIZE), RECORD_SIZE as isize);
+    assert_eq!(pread(fd, &mut buffer, 10 * RECORD_SIZE), RECORD_SIZE as isize);
+    assert_eq!(buffer, record(99));
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 5);
+    assert_eq!(pread(fd, &mut buffer, end as usize), 0);
+
+    // 越过文件末尾写入时，中间的空洞读出来是 0
+    assert_eq!(lseek(fd, end + 100, SEEK_SET), end + 100);
+    assert_eq!(write(fd, b"tail"), 4);
+    assert_eq!(lseek(fd, 0, SEEK_END), end + 104);
+    let mut hole = [0xffu8; 100];
+    assert_eq!(pread(fd, &mut hole, end as usize), 100);
+    assert!(hole.iter().all(|byte| *byte == 0));
+
+    // 写入之后超过文件最大长度时失败，文件大小不变
+    assert_eq!(pwrite(fd, b"x", usize::MAX), -1);
+    assert_eq!(pwrite(fd, b"x", 1 << 32), -1);
+    assert_eq!(pwrite(fd, b"x", MAX_FILE_SIZE), -1);
+    assert_eq!(lseek(fd, 1 << 32, SEEK_SET), -1);
+    assert_eq!(lseek(fd, MAX_FILE_SIZE as isize, SEEK_SET), MAX_FILE_SIZE as isize);
+    assert_eq!(write(fd, b"x"), -1);
+    assert_eq!(lseek(fd, 0, SEEK_END), end + 104);
+    close(fd);
+
+    // 标准输入输出不支持随机访问，只读文件不能 pwrite
+    assert_eq!(lseek(1, 0, SEEK_SET), -1);
+    assert_eq!(pread(0, &mut buffer, 0), -1);
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(pwrite(fd as usize, b"x", 0), -1);
+    close(fd as usize);
+    assert_eq!(unlink(path), 0);
+    println!("seektest passed!");
+    0
+}
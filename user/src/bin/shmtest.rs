@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, mprotect, shmat, shmctl, shmdt, shmget, waitpid, yield_, IPC_CREAT, IPC_EXCL,
+    IPC_PRIVATE, IPC_RMID, PROT_READ, PROT_WRITE, SHM_RDONLY,
+};
+
+const SLOTS: usize = 64;
+const MESSAGES: usize = 1000;
+
+// 放在共享内存中的环形缓冲区，生产者和消费者直接读写其中的数据，不经过内核复制
+#[repr(C)]
+struct Channel {
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    slots: [usize; SLOTS],
+}
+
+fn attach(shmid: usize) -> &'static mut Channel {
+    let addr = shmat(shmid, 0, 0);
+    assert!(addr > 0);
+    unsafe { &mut *(addr as usize as *mut Channel) }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let shmid = shmget(IPC_PRIVATE, core::mem::size_of::<Channel>(), 0);
+    assert!(shmid >= 0);
+    let shmid = shmid as usize;
+
+    let pid = fork();
+    if pid == 0 {
+        // 生产者
+        let channel = attach(shmid);
+        for i in 0..MESSAGES {
+            let head = channel.head.load(Ordering::Relaxed);
+            while head - channel.tail.load(Ordering::Acquire) == SLOTS {
+                yield_();
+            }
+            channel.slots[head % SLOTS] = i * i;
+            channel.head.store(head + 1, Ordering::Release);
+        }
+        shmdt(channel as *mut Channel as usize);
+        exit(0);
+    }
+    // 消费者，新建的共享内存段内容全为 0
+    let channel = attach(shmid);
+    for i in 0..MESSAGES {
+        let tail = channel.tail.load(Ordering::Relaxed);
+        while channel.head.load(Ordering::Acquire) == tail {
+            yield_();
+        }
+        assert_eq!(channel.slots[tail % SLOTS], i * i);
+        channel.tail.store(tail + 1, Ordering::Release);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // fork 之后子进程继承已经建立的映射
+    let pid = fork();
+    if pid == 0 {
+        channel.head.store(MESSAGES * 2, Ordering::Release);
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(channel.head.load(Ordering::Acquire), MESSAGES * 2);
+
+    // 同一个 key 对应同一个共享内存段
+    let key = 0x5348;
+    let keyed = shmget(key, 4096, IPC_CREAT);
+    assert!(keyed >= 0);
+    assert_eq!(shmget(key, 4096, 0), keyed);
+    assert_eq!(shmget(key, 4096, IPC_CREAT | IPC_EXCL), -1);
+    assert_eq!(shmget(key, 2 * 4096, 0), -1);
+    let readonly = shmat(keyed as usize, 0, SHM_RDONLY);
+    assert!(readonly > 0);
+    assert_eq!(unsafe { *(readonly as usize as *const u8) }, 0);
+    // 只读映射之后也不能通过 mprotect 改为可写
+    assert_eq!(
+        mprotect(readonly as usize, 4096, PROT_READ | PROT_WRITE),
+        -1
+    );
+    assert_eq!(mprotect(readonly as usize, 4096, PROT_READ), 0);
+    assert_eq!(shmdt(readonly as usize), 0);
+    assert_eq!(shmctl(keyed as usize, IPC_RMID), 0);
+    assert_eq!(shmget(key, 4096, 0), -1);
+    // 大小为 0 或者大到向上取整时溢出都会失败
+    assert_eq!(shmget(IPC_PRIVATE, 0, 0), -1);
+    assert_eq!(shmget(IPC_PRIVATE, usize::MAX, 0), -1);
+
+    // 删除之后已经建立的映射仍然可以使用，但不能再建立新的映射
+    assert_eq!(shmctl(shmid, IPC_RMID), 0);
+    assert_eq!(channel.head.load(Ordering::Acquire), MESSAGES * 2);
+    assert_eq!(shmat(shmid, 0, 0), -1);
+    let addr = channel as *mut Channel as usize;
+    assert_eq!(shmdt(addr), 0);
+    assert_eq!(shmdt(addr), -1);
+    println!("shmtest passed!");
+    0
+}
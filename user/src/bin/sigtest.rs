@@ -0,0 +1,129 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, kill, mmap, mprotect, munmap, sigaction, sigprocmask, sleep, wait, waitpid,
+    yield_, SignalAction, SignalFlags, PROT_READ, PROT_WRITE, SIGCHLD, SIGKILL, SIGSEGV, SIGTERM,
+    SIGUSR1, SIGUSR2, SIG_BLOCK, SIG_IGN, SIG_UNBLOCK,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+static USR1_COUNT: AtomicUsize = AtomicUsize::new(0);
+static CHLD_COUNT: AtomicUsize = AtomicUsize::new(0);
+static FAULT_PAGE: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn usr1_handler(signum: usize) {
+    assert_eq!(signum, SIGUSR1);
+    USR1_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+extern "C" fn chld_handler(signum: usize) {
+    assert_eq!(signum, SIGCHLD);
+    CHLD_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+// 让出错的页面变为可写之后返回，出错的指令会被重新执行
+extern "C" fn segv_handler(signum: usize) {
+    assert_eq!(signum, SIGSEGV);
+    let page = FAULT_PAGE.load(Ordering::SeqCst);
+    assert_eq!(mprotect(page, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+}
+
+fn set_handler(signum: usize, handler: usize) {
+    let action = SignalAction {
+        handler,
+        ..Default::default()
+    };
+    assert_eq!(sigaction(signum, Some(&action), None), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 信号在 kill 返回用户态之前就被处理，被打断的执行流的返回值不受影响
+    set_handler(SIGUSR1, usr1_handler as usize);
+    assert_eq!(kill(getpid() as usize, SIGUSR1), 0);
+    assert_eq!(USR1_COUNT.load(Ordering::SeqCst), 1);
+
+    // 被屏蔽的信号等到解除屏蔽时才被处理
+    assert_eq!(sigprocmask(SIG_BLOCK, Some(SignalFlags::SIGUSR1), None), 0);
+    assert_eq!(kill(getpid() as usize, SIGUSR1), 0);
+    assert_eq!(USR1_COUNT.load(Ordering::SeqCst), 1);
+    let mut old_set = SignalFlags::empty();
+    assert_eq!(
+        sigprocmask(SIG_UNBLOCK, Some(SignalFlags::SIGUSR1), Some(&mut old_set)),
+        0
+    );
+    assert_eq!(old_set, SignalFlags::SIGUSR1);
+    assert_eq!(USR1_COUNT.load(Ordering::SeqCst), 2);
+
+    // 被忽略的信号没有任何效果，SIGKILL 的处理方式不能修改
+    set_handler(SIGUSR2, SIG_IGN);
+    assert_eq!(kill(getpid() as usize, SIGUSR2), 0);
+    let mut old_action = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old_action)), 0);
+    assert_eq!(old_action.handler, usr1_handler as usize);
+    assert_eq!(sigaction(SIGKILL, Some(&SignalAction::default()), None), -1);
+    // act 指向无法读取的内存时 sigaction 失败，原来的处理方式保持不变
+    let unmapped = mmap(0, PAGE_SIZE, PROT_READ);
+    assert!(unmapped > 0);
+    assert_eq!(munmap(unmapped as usize, PAGE_SIZE), 0);
+    let bad_action = unsafe { &*(unmapped as usize as *const SignalAction) };
+    assert_eq!(sigaction(SIGUSR1, Some(bad_action), None), -1);
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old_action)), 0);
+    assert_eq!(old_action.handler, usr1_handler as usize);
+
+    // 子进程退出时父进程收到 SIGCHLD
+    set_handler(SIGCHLD, chld_handler as usize);
+    let pid = fork();
+    if pid == 0 {
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(CHLD_COUNT.load(Ordering::SeqCst), 1);
+
+    // SIGTERM 的默认动作终止进程，退出码为信号编号的相反数
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(kill(pid as usize, SIGTERM), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGTERM as i32));
+    assert_eq!(kill(pid as usize, 0), -1);
+
+    // 阻塞在 waitpid 中的进程也能被 SIGKILL 杀死
+    let pid = fork();
+    if pid == 0 {
+        if fork() == 0 {
+            sleep(200);
+            exit(0);
+        }
+        wait(&mut exit_code);
+        exit(0);
+    }
+    sleep(20);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+
+    // 处理函数可以修复非法访问之后让出错的指令重新执行
+    let page = mmap(0, PAGE_SIZE, PROT_READ);
+    assert!(page > 0);
+    FAULT_PAGE.store(page as usize, Ordering::SeqCst);
+    set_handler(SIGSEGV, segv_handler as usize);
+    let data = page as *mut u64;
+    unsafe {
+        data.write_volatile(0x1234_5678);
+        assert_eq!(data.read_volatile(), 0x1234_5678);
+    }
+    println!("sigtest passed!");
+    0
+}
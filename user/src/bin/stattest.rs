@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, link, lstat, mkdir, open, rmdir, stat, symlink, unlink, write, OpenFlags, Stat,
+    StatMode,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let data = [b's'; 1000];
+    let mut st = Stat::new();
+    assert_eq!(mkdir("/stattest\0"), 0);
+    let fd = open("/stattest/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.mode, StatMode::FILE);
+    assert_eq!(st.nlink, 1);
+    assert_eq!(st.size, 0);
+    assert_eq!(st.blocks, 0);
+    let ino = st.ino;
+
+    // 写入之后大小和占据的块数随之增长，用户在 read 之前就可以知道文件有多大
+    write(fd, &data);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, data.len() as u64);
+    assert_eq!(st.blocks, 2);
+    close(fd);
+
+    // 硬链接共享同一个 inode ，链接数随之变化
+    assert_eq!(link("/stattest/file\0", "/stattest/alias\0"), 0);
+    assert_eq!(stat("/stattest/alias\0", &mut st), 0);
+    assert_eq!(st.ino, ino);
+    assert_eq!(st.nlink, 2);
+
+    // stat 跟随符号链接，lstat 返回符号链接本身
+    assert_eq!(symlink("file\0", "/stattest/link\0"), 0);
+    assert_eq!(stat("/stattest/link\0", &mut st), 0);
+    assert_eq!(st.ino, ino);
+    assert_eq!(lstat("/stattest/link\0", &mut st), 0);
+    assert_eq!(st.mode, StatMode::LINK);
+    assert_eq!(st.size, 4);
+    assert_ne!(st.ino, ino);
+
+    // 目录的链接数是 2 加上子目录的个数
+    assert_eq!(mkdir("/stattest/sub\0"), 0);
+    assert_eq!(stat("/stattest\0", &mut st), 0);
+    assert_eq!(st.mode, StatMode::DIR);
+    assert_eq!(st.nlink, 3);
+
+    // 标准输出是字符设备，无效的文件描述符和不存在的路径返回 -1
+    assert_eq!(fstat(1, &mut st), 0);
+    assert_eq!(st.mode, StatMode::CHR);
+    assert_eq!(fstat(100, &mut st), -1);
+    assert_eq!(stat("/stattest/none\0", &mut st), -1);
+
+    assert_eq!(rmdir("/stattest/sub\0"), 0);
+    assert_eq!(unlink("/stattest/link\0"), 0);
+    assert_eq!(unlink("/stattest/alias\0"), 0);
+    assert_eq!(unlink("/stattest/file\0"), 0);
+    assert_eq!(rmdir("/stattest\0"), 0);
+    println!("stattest passed!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, open, unlink, waitpid, OpenFlags, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+// 比可用的物理内存更大，写满之后一部分页面只能换出到交换文件中
+const LEN: usize = 6 * 1024 * 1024;
+const PAGES: usize = LEN / PAGE_SIZE;
+
+// 前 modified 个页面的内容是页号加一，其余页面是页号
+fn check(buffer: &[u8], modified: usize) {
+    for page in 0..PAGES {
+        let expected = (if page < modified { page + 1 } else { page }) as u8;
+        assert_eq!(buffer[page * PAGE_SIZE], expected);
+        assert_eq!(buffer[page * PAGE_SIZE + PAGE_SIZE - 1], expected);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = mmap(0, LEN, PROT_READ | PROT_WRITE);
+    assert!(start > 0);
+    let buffer = unsafe { core::slice::from_raw_parts_mut(start as usize as *mut u8, LEN) };
+    for page in 0..PAGES {
+        buffer[page * PAGE_SIZE..(page + 1) * PAGE_SIZE].fill(page as u8);
+    }
+    // 换出的页面被重新访问时能换入原来的内容
+    check(buffer, 0);
+    // 交换文件对用户不可见，既不能打开也不能删除或者覆盖
+    assert_eq!(open("/.swap", OpenFlags::RDONLY), -1);
+    assert_eq!(open("/.swap", OpenFlags::CREATE | OpenFlags::WRONLY), -1);
+    assert_eq!(unlink("/.swap"), -1);
+    check(buffer, 0);
+
+    // fork 之后子进程修改一半页面的副本，父进程的页面保持不变
+    let pid = fork();
+    if pid == 0 {
+        check(buffer, 0);
+        for page in 0..PAGES / 2 {
+            buffer[page * PAGE_SIZE..(page + 1) * PAGE_SIZE].fill((page + 1) as u8);
+        }
+        check(buffer, PAGES / 2);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    check(buffer, 0);
+
+    assert_eq!(munmap(start as usize, LEN), 0);
+    println!("swaptest passed!");
+    0
+}
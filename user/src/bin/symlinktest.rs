@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, mkdir, open, read, readlink, rmdir, symlink, unlink, write, OpenFlags,
+};
+
+fn read_all(path: &str, buffer: &mut [u8]) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let len = read(fd as usize, buffer);
+    close(fd as usize);
+    len
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, symlinks!";
+    let mut buffer = [0u8; 64];
+    assert_eq!(mkdir("/symlinktest\0"), 0);
+    assert_eq!(mkdir("/symlinktest/v2\0"), 0);
+    let fd = open("/symlinktest/v2/prog\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    // 相对路径从符号链接所在的目录开始解析
+    assert_eq!(symlink("v2/prog\0", "/symlinktest/prog\0"), 0);
+    assert_eq!(read_all("/symlinktest/prog\0", &mut buffer), test_str.len() as isize);
+    assert_eq!(&buffer[..test_str.len()], test_str.as_bytes());
+    assert_eq!(readlink("/symlinktest/prog\0", &mut buffer), 7);
+    assert_eq!(&buffer[..7], b"v2/prog");
+    // 普通文件不是符号链接，同名的符号链接也不能重复创建
+    assert_eq!(readlink("/symlinktest/v2/prog\0", &mut buffer), -1);
+    assert_eq!(symlink("v2/prog\0", "/symlinktest/prog\0"), -1);
+
+    // 指向目录的符号链接可以出现在路径中间，也可以作为 chdir 的目标
+    assert_eq!(symlink("/symlinktest/v2\0", "/symlinktest/current\0"), 0);
+    assert_eq!(read_all("/symlinktest/current/prog\0", &mut buffer), test_str.len() as isize);
+    assert_eq!(symlink("current/prog\0", "/symlinktest/chain\0"), 0);
+    assert_eq!(read_all("/symlinktest/chain\0", &mut buffer), test_str.len() as isize);
+    assert_eq!(chdir("/symlinktest/current\0"), 0);
+    assert_eq!(read_all("prog\0", &mut buffer), test_str.len() as isize);
+    assert_eq!(chdir("/\0"), 0);
+
+    // 循环的符号链接在跟随次数超过上限后查找失败
+    assert_eq!(symlink("loop_b\0", "/symlinktest/loop_a\0"), 0);
+    assert_eq!(symlink("loop_a\0", "/symlinktest/loop_b\0"), 0);
+    assert_eq!(open("/symlinktest/loop_a\0", OpenFlags::RDONLY), -1);
+    assert_eq!(open("/symlinktest/loop_a\0", OpenFlags::CREATE), -1);
+    // 悬空的符号链接也无法打开
+    assert_eq!(symlink("nowhere\0", "/symlinktest/dangling\0"), 0);
+    assert_eq!(open("/symlinktest/dangling\0", OpenFlags::RDONLY), -1);
+
+    // 删除符号链接不会影响它指向的文件
+    for name in [
+        "/symlinktest/prog\0",
+        "/symlinktest/chain\0",
+        "/symlinktest/current\0",
+        "/symlinktest/loop_a\0",
+        "/symlinktest/loop_b\0",
+        "/symlinktest/dangling\0",
+    ] {
+        assert_eq!(unlink(name), 0);
+    }
+    assert_eq!(read_all("/symlinktest/v2/prog\0", &mut buffer), test_str.len() as isize);
+    assert_eq!(unlink("/symlinktest/v2/prog\0"), 0);
+    assert_eq!(rmdir("/symlinktest/v2\0"), 0);
+    assert_eq!(rmdir("/symlinktest\0"), 0);
+    println!("symlinktest passed!");
+    0
+}
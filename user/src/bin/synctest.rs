@@ -0,0 +1,155 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use user_lib::{
+    exit, fork, kill, mutex_create, mutex_lock, mutex_unlock, semaphore_create, semaphore_down,
+    semaphore_up, sleep, thread_create, waitpid, waittid, yield_, Condvar, Mutex, Semaphore,
+    SIGKILL,
+};
+
+const THREAD_COUNT: usize = 4;
+const ROUNDS: usize = 200;
+const BUFFER_SIZE: usize = 4;
+const ITEMS: usize = 50;
+
+struct Shared {
+    counter: Mutex<usize>,
+    buffer: Mutex<VecDeque<usize>>,
+    empty: Semaphore,
+    full: Semaphore,
+    ready: Mutex<bool>,
+    ready_cond: Condvar,
+}
+
+// 线程通过参数拿到共享状态的一个引用计数
+fn shared_from_arg(arg: usize) -> Arc<Shared> {
+    unsafe { Arc::from_raw(arg as *const Shared) }
+}
+
+fn shared_to_arg(shared: &Arc<Shared>) -> usize {
+    Arc::into_raw(shared.clone()) as usize
+}
+
+// 在临界区中让出处理器，没有锁的话其它线程的修改会被覆盖
+extern "C" fn adder(arg: usize) -> ! {
+    let shared = shared_from_arg(arg);
+    for _ in 0..ROUNDS {
+        let mut counter = shared.counter.lock();
+        let value = *counter;
+        yield_();
+        *counter = value + 1;
+    }
+    exit(0)
+}
+
+extern "C" fn producer(arg: usize) -> ! {
+    let shared = shared_from_arg(arg);
+    for item in 1..=ITEMS {
+        shared.empty.down();
+        shared.buffer.lock().push_back(item);
+        shared.full.up();
+    }
+    exit(0)
+}
+
+extern "C" fn notifier(arg: usize) -> ! {
+    let shared = shared_from_arg(arg);
+    sleep(10);
+    *shared.ready.lock() = true;
+    shared.ready_cond.signal();
+    exit(0)
+}
+
+fn wait_thread(tid: isize) {
+    assert!(tid > 0);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let shared = Arc::new(Shared {
+        counter: Mutex::new(0),
+        buffer: Mutex::new(VecDeque::new()),
+        empty: Semaphore::new(BUFFER_SIZE),
+        full: Semaphore::new(0),
+        ready: Mutex::new(false),
+        ready_cond: Condvar::new(),
+    });
+
+    // 互斥锁保护的计数器
+    let mut tids = [0isize; THREAD_COUNT];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, shared_to_arg(&shared));
+    }
+    for tid in tids {
+        wait_thread(tid);
+    }
+    assert_eq!(*shared.counter.lock(), THREAD_COUNT * ROUNDS);
+
+    // 用两个信号量实现的有界缓冲区
+    let tid = thread_create(producer as usize, shared_to_arg(&shared));
+    let mut sum = 0;
+    for _ in 0..ITEMS {
+        shared.full.down();
+        let item = shared.buffer.lock().pop_front().unwrap();
+        sum += item;
+        shared.empty.up();
+    }
+    wait_thread(tid);
+    assert_eq!(sum, ITEMS * (ITEMS + 1) / 2);
+
+    // 在条件变量上等待另一个线程设置标志
+    let tid = thread_create(notifier as usize, shared_to_arg(&shared));
+    let mut ready = shared.ready.lock();
+    while !*ready {
+        ready = shared.ready_cond.wait(ready);
+    }
+    drop(ready);
+    wait_thread(tid);
+
+    // 自旋锁以及错误的编号
+    let mutex_id = mutex_create();
+    assert!(mutex_id >= 0);
+    let mutex_id = mutex_id as usize;
+    assert_eq!(mutex_lock(mutex_id), 0);
+    assert_eq!(mutex_unlock(mutex_id), 0);
+    assert_eq!(mutex_unlock(mutex_id), -1);
+    assert_eq!(mutex_lock(100), -1);
+    assert_eq!(semaphore_up(100), -1);
+
+    // fork 出的子进程与父进程共享信号量
+    let sem_id = semaphore_create(0);
+    assert!(sem_id >= 0);
+    let sem_id = sem_id as usize;
+    let pid = fork();
+    if pid == 0 {
+        sleep(10);
+        semaphore_up(sem_id);
+        exit(0);
+    }
+    assert_eq!(semaphore_down(sem_id), 0);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 等待信号量的进程仍然可以被 SIGKILL 杀死
+    let pid = fork();
+    if pid == 0 {
+        semaphore_down(sem_id);
+        exit(0);
+    }
+    sleep(10);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    println!("synctest passed!");
+    0
+}
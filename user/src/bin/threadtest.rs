@@ -0,0 +1,111 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    close, exit, fork, gettid, pipe, read, thread_create, waitpid, waittid, write, yield_,
+};
+
+const THREAD_COUNT: usize = 4;
+const ROUNDS: usize = 1000;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static TIDS: [AtomicUsize; THREAD_COUNT] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+// 线程函数通过 a0 接收参数，按照 C 的调用约定定义。每个线程都有自己的用户栈，局部变量互不干扰；
+// 全局变量在线程之间共享
+extern "C" fn worker(index: usize) -> ! {
+    TIDS[index].store(gettid() as usize, Ordering::SeqCst);
+    let mut local = 0;
+    for _ in 0..ROUNDS {
+        local += 1;
+        COUNTER.fetch_add(1, Ordering::SeqCst);
+        yield_();
+    }
+    assert_eq!(local, ROUNDS);
+    exit(100 + index as i32)
+}
+
+extern "C" fn reader(fd: usize) -> ! {
+    let mut buffer = [0u8; 16];
+    let len = read(fd, &mut buffer);
+    assert_eq!(&buffer[..len as usize], b"ping");
+    exit(0)
+}
+
+extern "C" fn spinner(_arg: usize) -> ! {
+    loop {
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let mut tids = [0usize; THREAD_COUNT];
+    for (index, tid) in tids.iter_mut().enumerate() {
+        let ret = thread_create(worker as usize, index);
+        assert!(ret > 0);
+        *tid = ret as usize;
+    }
+    // 还有其它线程的进程不能 fork ，也不能等待自己或者不存在的线程
+    assert_eq!(fork(), -1);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waittid(0, &mut exit_code), -1);
+    assert_eq!(waittid(100, &mut exit_code), -1);
+    for (index, &tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+        assert_eq!(exit_code, 100 + index as i32);
+        assert_eq!(TIDS[index].load(Ordering::SeqCst), tid);
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREAD_COUNT * ROUNDS);
+    // 回收之后的线程编号不能再被等待
+    assert_eq!(waittid(tids[0], &mut exit_code), -1);
+
+    // 一个线程阻塞在管道上读的时候其它线程照常运行
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let tid = thread_create(reader as usize, pipe_fd[0]);
+    assert!(tid > 0);
+    for _ in 0..10 {
+        yield_();
+    }
+    assert_eq!(write(pipe_fd[1], b"ping"), 4);
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    assert_eq!(exit_code, 0);
+
+    // 主线程退出时整个进程随之退出，其它线程也被结束
+    let pid = fork();
+    if pid == 0 {
+        assert!(thread_create(spinner as usize, 0) > 0);
+        exit(7);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+
+    // 其它线程阻塞在管道上时主线程退出：进程仍然可以被回收，它持有的管道写端也被关闭
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert!(thread_create(reader as usize, pipe_fd[0]) > 0);
+        for _ in 0..10 {
+            yield_();
+        }
+        exit(8);
+    }
+    close(pipe_fd[1]);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 8);
+    assert_eq!(read(pipe_fd[0], &mut [0u8; 1]), 0);
+    close(pipe_fd[0]);
+    println!("threadtest passed!");
+    0
+}
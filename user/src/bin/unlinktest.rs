@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, unlink, write, OpenFlags};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let path = "unlinktest_tmp\0";
+    let data = [b'x'; 4096];
+    // easy-fs only has 4096 inodes, so this fails unless unlink reclaims them
+    for i in 0..5000 {
+        let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd > 0, "create failed in round {}", i);
+        assert_eq!(write(fd as usize, &data), data.len() as isize);
+        close(fd as usize);
+        assert_eq!(unlink(path), 0);
+    }
+    assert_eq!(open(path, OpenFlags::RDONLY), -1);
+    assert_eq!(unlink(path), -1);
+    println!("unlinktest passed!");
+    0
+}
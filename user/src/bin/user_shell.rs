@@ -9,14 +9,151 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
+//shell 需要及时响应输入，使用步长调度时给它较高的优先级，它启动的程序恢复为默认的优先级
+const SHELL_NICE: isize = -10;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{exec, fork, waitpid};
+use user_lib::{close, dup2, exec, fork, open, pipe, setpriority, waitpid, OpenFlags};
+
+//管道中的一个进程：要执行的程序以及它的输入输出重定向，文件名都以 \0 结尾
+#[derive(Debug)]
+struct ProcessArguments {
+    input: String,
+    output: String,
+    args: Vec<String>,
+}
+
+impl ProcessArguments {
+    //解析形如 "prog < in.txt > out.txt" 的命令，< 和 > 后面紧跟重定向的文件名
+    pub fn new(command: &str) -> Option<Self> {
+        let mut input = String::new();
+        let mut output = String::new();
+        let mut args: Vec<String> = Vec::new();
+        let mut tokens = command.split(' ').filter(|token| !token.is_empty());
+        while let Some(token) = tokens.next() {
+            match token {
+                "<" => input = String::from(tokens.next()?) + "\0",
+                ">" => output = String::from(tokens.next()?) + "\0",
+                _ => args.push(String::from(token) + "\0"),
+            }
+        }
+        if args.is_empty() {
+            None
+        } else {
+            Some(Self {
+                input,
+                output,
+                args,
+            })
+        }
+    }
+}
+
+//在子进程中打开重定向的文件，并把它复制到标准输入或标准输出的位置
+fn redirect(path: &str, flags: OpenFlags, target_fd: usize) -> bool {
+    let fd = open(path, flags);
+    if fd < 0 {
+        println!("Error when opening file {}", path.trim_end_matches('\0'));
+        return false;
+    }
+    assert_eq!(dup2(fd as usize, target_fd), target_fd as isize);
+    close(fd as usize);
+    true
+}
+
+//执行一行命令，命令之间用 | 连接，前一个进程的标准输出通过管道连到后一个进程的标准输入
+fn run_line(line: &str) {
+    let process_arguments_list: Option<Vec<ProcessArguments>> =
+        line.split('|').map(ProcessArguments::new).collect();
+    let process_arguments_list = match process_arguments_list {
+        Some(list) => list,
+        None => {
+            println!("Invalid command!");
+            return;
+        }
+    };
+    let count = process_arguments_list.len();
+    //只有第一个进程可以重定向输入，只有最后一个进程可以重定向输出
+    for (i, process_args) in process_arguments_list.iter().enumerate() {
+        if (i != 0 && !process_args.input.is_empty())
+            || (i != count - 1 && !process_args.output.is_empty())
+        {
+            println!("Invalid redirection in a pipeline!");
+            return;
+        }
+    }
+    //在相邻的两个进程之间创建管道
+    let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
+    for _ in 0..count - 1 {
+        let mut pipe_fd = [0usize; 2];
+        pipe(&mut pipe_fd);
+        pipes_fd.push(pipe_fd);
+    }
+    let mut children: Vec<isize> = Vec::new();
+    for (i, process_args) in process_arguments_list.iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            // child process
+            setpriority(0, 0);
+            if !process_args.input.is_empty()
+                && !redirect(process_args.input.as_str(), OpenFlags::RDONLY, 0)
+            {
+                user_lib::exit(-4);
+            }
+            if !process_args.output.is_empty()
+                && !redirect(
+                    process_args.output.as_str(),
+                    OpenFlags::CREATE | OpenFlags::WRONLY,
+                    1,
+                )
+            {
+                user_lib::exit(-4);
+            }
+            //从前一个进程的管道读端接收输入
+            if i > 0 {
+                assert_eq!(dup2(pipes_fd[i - 1][0], 0), 0);
+            }
+            //向后一个进程的管道写端发送输出
+            if i < count - 1 {
+                assert_eq!(dup2(pipes_fd[i][1], 1), 1);
+            }
+            //关闭从 shell 继承来的所有管道端口
+            for pipe_fd in pipes_fd.iter() {
+                close(pipe_fd[0]);
+                close(pipe_fd[1]);
+            }
+            //参数数组以空指针结尾
+            let mut args_addr: Vec<*const u8> =
+                process_args.args.iter().map(|arg| arg.as_ptr()).collect();
+            args_addr.push(core::ptr::null::<u8>());
+            if exec(process_args.args[0].as_str(), args_addr.as_slice()) == -1 {
+                println!("Error when executing!");
+                user_lib::exit(-4);
+            }
+            unreachable!();
+        } else {
+            children.push(pid);
+        }
+    }
+    //shell 自己不能持有管道的写端，否则读端永远读不到 EOF
+    for pipe_fd in pipes_fd.iter() {
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+    }
+    for pid in children.into_iter() {
+        let mut exit_code: i32 = 0;
+        let exit_pid = waitpid(pid as usize, &mut exit_code);
+        assert_eq!(pid, exit_pid);
+        println!("Shell: Process {} exited with code {}", pid, exit_code);
+    }
+}
 
 #[no_mangle]
 pub fn main() -> i32 {
     println!("Rust user shell");
+    setpriority(0, SHELL_NICE);
     let mut line: String = String::new();
     print!(">> ");
     loop {
@@ -25,21 +162,7 @@ pub fn main() -> i32 {
             LF | CR => {
                 println!("");
                 if !line.is_empty() {
-                    line.push('\0');
-                    let pid = fork();
-                    if pid == 0 {
-                        // child process
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as usize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
-                    }
+                    run_line(line.as_str());
                     line.clear();
                 }
                 print!(">> ");
@@ -10,6 +10,31 @@ static TESTS: &[&str] = &[
     "forktest2\0",
     "sleep\0",
     "yield\0",
+    "dirtest\0",
+    "unlinktest\0",
+    "linktest\0",
+    "symlinktest\0",
+    "stattest\0",
+    "seektest\0",
+    "pipetest\0",
+    "pipe_large_test\0",
+    "duptest\0",
+    "argtest\0",
+    "waittest\0",
+    "cowtest\0",
+    "mmaptest\0",
+    "brktest\0",
+    "lazytest\0",
+    "swaptest\0",
+    "filemaptest\0",
+    "mprotecttest\0",
+    "shmtest\0",
+    "meminfotest\0",
+    "sigtest\0",
+    "threadtest\0",
+    "synctest\0",
+    "futextest\0",
+    "prioritytest\0",
 ];
 
 use user_lib::{exec, fork, waitpid};
@@ -20,7 +45,7 @@ pub fn main() -> i32 {
         println!("Usertests: Running {}", test);
         let pid = fork();
         if pid == 0 {
-            exec(*test);
+            exec(*test, &[test.as_ptr(), core::ptr::null::<u8>()]);
             panic!("unreachable!");
         } else {
             let mut exit_code: i32 = Default::default();
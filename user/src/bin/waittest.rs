@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, sleep, wait, waitpid, waitpid_nohang, PROT_READ};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut exit_code: i32 = 0;
+    // 没有子进程时无论是否阻塞都返回 -1
+    assert_eq!(waitpid_nohang(-1, &mut exit_code), -1);
+    assert_eq!(wait(&mut exit_code), -1);
+
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        exit(7);
+    }
+    // 子进程还在睡眠，WNOHANG 立即返回 0
+    assert_eq!(waitpid_nohang(pid, &mut exit_code), 0);
+    // 阻塞等待直到子进程退出时被唤醒
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    assert_eq!(waitpid_nohang(pid, &mut exit_code), -1);
+
+    // 退出码不能写入时返回 -1 ，子进程不会被回收，之后仍然可以正常等待它
+    let readonly = mmap(0, 4096, PROT_READ);
+    assert!(readonly > 0);
+    let pid = fork();
+    if pid == 0 {
+        exit(3);
+    }
+    sleep(10);
+    let bad_exit_code = unsafe { &mut *(readonly as usize as *mut i32) };
+    assert_eq!(waitpid(pid as usize, bad_exit_code), -1);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 3);
+    assert_eq!(munmap(readonly as usize, 4096), 0);
+
+    // 多个子进程先后退出，wait 依次回收它们
+    for i in 0..3 {
+        if fork() == 0 {
+            sleep(10 * (i + 1));
+            exit(i as i32);
+        }
+    }
+    let mut exited = [false; 3];
+    for _ in 0..3 {
+        assert!(wait(&mut exit_code) > 0);
+        exited[exit_code as usize] = true;
+    }
+    assert!(exited.iter().all(|e| *e));
+    assert_eq!(wait(&mut exit_code), -1);
+    println!("waittest passed!");
+    0
+}
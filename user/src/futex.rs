@@ -0,0 +1,112 @@
+//! 完全在用户态实现的锁和条件变量，没有竞争时只需要一次原子操作，只有需要睡眠或者唤醒其它线程时才进入内核。
+//! 它们不持有内核对象，全为 0 的内存就是未上锁的状态，所以可以定义为全局变量，也可以放在共享内存中由多个进程使用
+use crate::{futex_wait, futex_wake};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+//锁被占用并且可能有线程在等待，释放锁时需要进入内核唤醒一个等待者
+const CONTENDED: u32 = 2;
+
+//保护类型为 T 的数据的锁，只能通过 lock 返回的 FutexMutexGuard 访问数据，FutexMutexGuard 被丢弃时释放锁
+pub struct FutexMutex<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for FutexMutex<T> {}
+
+impl<T> FutexMutex<T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            data: UnsafeCell::new(data),
+        }
+    }
+    pub fn lock(&self) -> FutexMutexGuard<'_, T> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            self.lock_contended();
+        }
+        FutexMutexGuard { mutex: self }
+    }
+    pub fn try_lock(&self) -> Option<FutexMutexGuard<'_, T>> {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| FutexMutexGuard { mutex: self })
+    }
+    //将状态设为 CONTENDED 之后再睡眠，这样持有锁的线程释放锁时知道要唤醒等待者。
+    //抢到锁时状态也是 CONTENDED ，释放时可能多进入一次内核，但不会漏掉其它等待者
+    fn lock_contended(&self) {
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            futex_wait(&self.state, CONTENDED);
+        }
+    }
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+pub struct FutexMutexGuard<'a, T> {
+    mutex: &'a FutexMutex<T>,
+}
+
+impl<T> Deref for FutexMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for FutexMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for FutexMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+//与 FutexMutex 配合使用的条件变量。每次通知都让序号加一，等待者在释放锁之前记下序号，
+//所以在释放锁和睡眠之间发生的通知会让 futex_wait 立即返回，不会丢失。wait 可能被信号提前唤醒，
+//需要在循环中检查等待的条件
+pub struct FutexCondvar {
+    seq: AtomicU32,
+}
+
+impl FutexCondvar {
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+        }
+    }
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        futex_wake(&self.seq, 1);
+    }
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        futex_wake(&self.seq, usize::MAX);
+    }
+    //释放 guard 持有的锁并等待，返回时重新持有这个锁。其它线程可能也在等待这个锁，
+    //所以按有竞争的方式重新加锁
+    pub fn wait<'a, T>(&self, guard: FutexMutexGuard<'a, T>) -> FutexMutexGuard<'a, T> {
+        let seq = self.seq.load(Ordering::Acquire);
+        let mutex = guard.mutex;
+        drop(guard);
+        futex_wait(&self.seq, seq);
+        mutex.lock_contended();
+        FutexMutexGuard { mutex }
+    }
+}
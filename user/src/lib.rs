@@ -5,70 +5,115 @@
 
 #[macro_use]
 pub mod console;
+mod futex;
 mod lang_items;
+mod sync;
 mod syscall;
 
 extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
+use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::arch::global_asm;
+use core::ptr::NonNull;
+use core::sync::atomic::AtomicU32;
 use syscall::*;
+pub use futex::{FutexCondvar, FutexMutex, FutexMutexGuard};
+pub use sync::{Condvar, Mutex, MutexGuard, Semaphore};
 const USER_HEAP_SIZE: usize = 32768;
+//堆空间用尽时每次通过 sbrk 至少扩展这么多字节
+const HEAP_GROW_SIZE: usize = 4096 * 4;
 
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
+//以 .bss 中的 HEAP_SPACE 作为初始的堆，分配失败时通过 sbrk 向内核申请更多的内存加入堆中再重试
+struct GrowableHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        //伙伴分配器需要一整块按自身大小对齐的空闲块，所以新申请的区域要从这样的对齐位置开始
+        let block_size = layout
+            .size()
+            .max(layout.align())
+            .next_power_of_two()
+            .max(HEAP_GROW_SIZE);
+        let old_brk = sbrk(0);
+        if old_brk < 0 {
+            return core::ptr::null_mut();
+        }
+        let old_brk = old_brk as usize;
+        let new_brk = (old_brk + block_size - 1) / block_size * block_size + block_size;
+        if sbrk((new_brk - old_brk) as isize) < 0 {
+            return core::ptr::null_mut();
+        }
+        heap.add_to_heap(old_brk, new_brk);
+        heap.alloc(layout)
+            .map_or(core::ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
+
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: GrowableHeap = GrowableHeap(LockedHeap::empty());
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout);
 }
 
-//在应用中使能动态内存分配
+//在应用中使能动态内存分配，并把内核放在用户栈上的命令行参数整理成 &str 数组交给 main
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
     unsafe {
-        HEAP.lock()
+        HEAP.0
+            .lock()
             .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
     }
-    exit(main());
+    let mut v: Vec<&'static str> = Vec::new();
+    for i in 0..argc {
+        let str_start =
+            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
+        let len = (0usize..)
+            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
+            .unwrap();
+        v.push(unsafe {
+            core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                str_start as *const u8,
+                len,
+            ))
+        });
+    }
+    exit(main(argc, v.as_slice()));
 }
 
-//等待任意一个子进程结束
+//等待任意一个子进程结束，子进程都还没有退出时在内核中阻塞
 pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    sys_waitpid(-1, exit_code as *mut _, 0)
 }
 
 //等待一个进程标识符的值为pid的子进程结束
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        //当 sys_waitpid 返回值为 -2 ，即要等待的子进程存在但它却尚未退出的时候，
-        //我们调用 yield_ 主动交出 CPU 使用权，待下次 CPU 使用权被内核
-        //交还给它的时候再次调用 sys_waitpid 查看要等待的子进程是否退出。
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+//不阻塞的 waitpid ，pid 为 -1 时表示任意一个子进程。子进程都还没有退出的时候返回 0
+pub fn waitpid_nohang(pid: isize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid, exit_code as *mut _, WNOHANG)
 }
 
 #[linkage = "weak"]
 #[no_mangle]
-fn main() -> i32 {
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
     panic!("Cannot find main!");
 }
 
@@ -83,6 +128,185 @@ bitflags! {
     }
 }
 
+bitflags! {
+    //文件类型，与 Linux 中 st_mode 的高位相同
+    pub struct StatMode: u32 {
+        const NULL = 0;
+        const FIFO = 0o010000;
+        const CHR = 0o020000;
+        const DIR = 0o040000;
+        const FILE = 0o100000;
+        const LINK = 0o120000;
+    }
+}
+
+//fstat/fstatat 返回的文件元数据
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: StatMode,
+    pub nlink: u32,
+    pub size: u64,
+    pub blocks: u64,
+}
+
+impl Stat {
+    pub fn new() -> Self {
+        Self {
+            dev: 0,
+            ino: 0,
+            mode: StatMode::NULL,
+            nlink: 0,
+            size: 0,
+            blocks: 0,
+        }
+    }
+}
+
+bitflags! {
+    //信号的集合，编号为 n 的信号对应第 n 位
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGXCPU = 1 << 24;
+        const SIGXFSZ = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF = 1 << 27;
+        const SIGWINCH = 1 << 28;
+        const SIGIO = 1 << 29;
+        const SIGPWR = 1 << 30;
+        const SIGSYS = 1 << 31;
+    }
+}
+
+impl SignalFlags {
+    //编号为 signum 的信号
+    pub fn from_signum(signum: usize) -> Self {
+        Self::from_bits_truncate(1 << signum)
+    }
+}
+
+//信号的处理方式，布局与内核中的定义相同。handler 是 fn(signum: usize) 类型的处理函数的地址，
+//或者 SIG_DFL/SIG_IGN ；mask 是处理函数执行期间额外屏蔽的信号；restorer 由 sigaction 自动填写
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+//sysinfo 返回的内存使用情况
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct SysInfo {
+    pub total_frames: usize,
+    pub free_frames: usize,
+    pub heap_total: usize,
+    pub heap_used: usize,
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//lseek 中 whence 的取值
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+//*at 系列系统调用中表示从当前工作目录开始查找
+pub const AT_FDCWD: isize = -100;
+//fstatat 中表示不跟随路径最后一级的符号链接
+pub const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+//unlinkat 中表示删除的是目录
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+//waitpid 的 options ：子进程都还没有退出时立即返回而不是阻塞
+pub const WNOHANG: usize = 1;
+
+//mmap 的 prot 和 flags
+pub const PROT_READ: usize = 0x1;
+pub const PROT_WRITE: usize = 0x2;
+pub const PROT_EXEC: usize = 0x4;
+pub const MAP_SHARED: usize = 0x01;
+pub const MAP_PRIVATE: usize = 0x02;
+pub const MAP_ANONYMOUS: usize = 0x20;
+//System V 共享内存的 key 、flags 和 cmd
+pub const IPC_PRIVATE: usize = 0;
+pub const IPC_CREAT: usize = 0o1000;
+pub const IPC_EXCL: usize = 0o2000;
+pub const SHM_RDONLY: usize = 0o10000;
+pub const IPC_RMID: usize = 0;
+//futex 的 futex_op
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+//setpriority 和 getpriority 的 which
+pub const PRIO_PROCESS: usize = 0;
+
+//信号的编号
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+//特殊的信号处理函数：执行默认动作或者忽略信号
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+//sigprocmask 的 how
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
 pub fn open(path: &str, flags: OpenFlags) -> isize {
     sys_open(path, flags.bits)
 }
@@ -95,6 +319,71 @@ pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
 
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD, path, 0)
+}
+
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+
+pub fn symlink(target: &str, path: &str) -> isize {
+    sys_symlinkat(target, AT_FDCWD, path)
+}
+
+pub fn readlink(path: &str, buf: &mut [u8]) -> isize {
+    sys_readlinkat(AT_FDCWD, path, buf)
+}
+
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st)
+}
+
+pub fn stat(path: &str, st: &mut Stat) -> isize {
+    sys_fstatat(AT_FDCWD, path, st, 0)
+}
+
+pub fn lstat(path: &str, st: &mut Stat) -> isize {
+    sys_fstatat(AT_FDCWD, path, st, AT_SYMLINK_NOFOLLOW)
+}
+
+pub fn rmdir(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, AT_REMOVEDIR)
+}
+
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+
+//与 dup3 不同，oldfd 与 newfd 相同时只检查 oldfd 是否有效，有效时直接返回 newfd
+pub fn dup2(oldfd: usize, newfd: usize) -> isize {
+    if oldfd == newfd {
+        let mut st = Stat::new();
+        if sys_fstat(oldfd, &mut st) != 0 {
+            return -1;
+        }
+        return newfd as isize;
+    }
+    sys_dup3(oldfd, newfd, 0)
+}
+
+//pipe_fd[0] 为读端，pipe_fd[1] 为写端
+pub fn pipe(pipe_fd: &mut [usize]) -> isize {
+    sys_pipe(pipe_fd)
+}
+
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
@@ -102,6 +391,17 @@ pub fn read(fd: usize, buf: &mut [u8]) -> isize {
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> isize {
+    sys_pread64(fd, buf, offset)
+}
+
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> isize {
+    sys_pwrite64(fd, buf, offset)
+}
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
@@ -117,8 +417,191 @@ pub fn getpid() -> isize {
 pub fn fork() -> isize {
     sys_fork()
 }
-pub fn exec(path: &str) -> isize {
-    sys_exec(path)
+//在当前进程中创建一个从 entry 开始执行的线程，arg 作为 entry 的参数。entry 不能返回，必须调用 exit
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    sys_thread_create(entry, arg)
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+//等待编号为 tid 的线程退出，成功时返回 tid
+pub fn waittid(tid: usize, exit_code: &mut i32) -> isize {
+    sys_waittid(tid, exit_code as *mut _)
+}
+
+//自旋锁，锁被占用时让出处理器之后重试
+pub fn mutex_create() -> isize {
+    sys_mutex_create(false)
+}
+//阻塞锁，锁被占用时阻塞等待
+pub fn mutex_blocking_create() -> isize {
+    sys_mutex_create(true)
+}
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    sys_mutex_lock(mutex_id)
+}
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    sys_mutex_unlock(mutex_id)
+}
+pub fn semaphore_create(res_count: usize) -> isize {
+    sys_semaphore_create(res_count)
+}
+pub fn semaphore_up(sem_id: usize) -> isize {
+    sys_semaphore_up(sem_id)
+}
+pub fn semaphore_down(sem_id: usize) -> isize {
+    sys_semaphore_down(sem_id)
+}
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    sys_condvar_signal(condvar_id)
+}
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(condvar_id, mutex_id)
+}
+//futex 的值仍为 val 时阻塞，直到其它线程 futex_wake 。值已经改变或者被信号打断时返回 -1 ，调用者需要重新检查
+pub fn futex_wait(futex: &AtomicU32, val: u32) -> isize {
+    sys_futex(futex as *const AtomicU32 as *const u32, FUTEX_WAIT, val as usize)
+}
+//唤醒最多 count 个在 futex 上等待的线程，返回唤醒的数量
+pub fn futex_wake(futex: &AtomicU32, count: usize) -> isize {
+    sys_futex(futex as *const AtomicU32 as *const u32, FUTEX_WAKE, count)
+}
+pub fn kill(pid: usize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+
+//信号处理函数返回到这里，通过 sigreturn 恢复被打断的执行流。此时 sp 必须恰好指向内核保存的现场，
+//所以不能写成会调整栈指针的普通函数
+global_asm!(
+    ".section .text",
+    ".globl __sigreturn",
+    "__sigreturn:",
+    "li a7, 139",
+    "ecall",
+);
+
+//设置信号 signum 的处理方式，action 为 None 时只读取原来的处理方式
+pub fn sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> isize {
+    extern "C" {
+        fn __sigreturn();
+    }
+    let action = action.map(|action| SignalAction {
+        restorer: __sigreturn as usize,
+        ..*action
+    });
+    sys_sigaction(
+        signum,
+        action
+            .as_ref()
+            .map_or(core::ptr::null(), |action| action as *const _),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut _),
+    )
+}
+//按照 how 修改信号屏蔽字，set 为 None 时只读取原来的屏蔽字
+pub fn sigprocmask(
+    how: usize,
+    set: Option<SignalFlags>,
+    old_set: Option<&mut SignalFlags>,
+) -> isize {
+    let set = set.map(|set| set.bits());
+    let mut old_bits: u32 = 0;
+    let ret = sys_sigprocmask(
+        how,
+        set.as_ref().map_or(core::ptr::null(), |set| set as *const u32),
+        &mut old_bits as *mut u32,
+    );
+    if let Some(old_set) = old_set {
+        *old_set = SignalFlags::from_bits_truncate(old_bits);
+    }
+    ret
+}
+//设置进程的 nice 值，pid 为 0 时表示当前进程
+pub fn setpriority(pid: usize, nice: isize) -> isize {
+    sys_setpriority(PRIO_PROCESS, pid, nice)
+}
+//读取进程的 nice 值，pid 为 0 时表示当前进程，进程不存在时返回 None
+pub fn getpriority(pid: usize) -> Option<isize> {
+    match sys_getpriority(PRIO_PROCESS, pid) {
+        -1 => None,
+        ret => Some(20 - ret),
+    }
+}
+//将当前进程的 nice 值增加 inc ，成功返回 0
+pub fn nice(inc: isize) -> isize {
+    sys_setpriority(PRIO_PROCESS, 0, getpriority(0).unwrap() + inc)
+}
+//args 是以空指针结尾的参数数组，其中的每个字符串都需要以 \0 结尾
+pub fn exec(path: &str, args: &[*const u8]) -> isize {
+    sys_exec(path, args, &[core::ptr::null::<u8>()])
+}
+pub fn execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_exec(path, args, envs)
+}
+
+//映射一段匿名的私有内存，start 为 0 时由内核选择位置
+pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
+    sys_mmap(start, len, prot, MAP_PRIVATE | MAP_ANONYMOUS, usize::MAX, 0)
+}
+//映射文件 fd 中从 offset 开始的内容，flags 为 MAP_SHARED 或 MAP_PRIVATE
+pub fn mmap_file(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    sys_mmap(start, len, prot, flags, fd, offset)
+}
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(start, len, prot)
+}
+pub fn msync(start: usize, len: usize) -> isize {
+    sys_msync(start, len, 0)
+}
+pub fn munmap(start: usize, len: usize) -> isize {
+    sys_munmap(start, len)
+}
+
+pub fn sysinfo(info: &mut SysInfo) -> isize {
+    sys_sysinfo(info)
+}
+
+pub fn shmget(key: usize, size: usize, flags: usize) -> isize {
+    sys_shmget(key, size, flags)
+}
+pub fn shmat(shmid: usize, addr: usize, flags: usize) -> isize {
+    sys_shmat(shmid, addr, flags)
+}
+pub fn shmdt(addr: usize) -> isize {
+    sys_shmdt(addr)
+}
+pub fn shmctl(shmid: usize, cmd: usize) -> isize {
+    sys_shmctl(shmid, cmd)
+}
+
+//设置程序断点，返回设置之后的程序断点
+pub fn brk(addr: usize) -> isize {
+    sys_brk(addr)
+}
+//将程序断点移动 increment 字节，成功时返回原来的程序断点，否则返回 -1
+pub fn sbrk(increment: isize) -> isize {
+    let old_brk = sys_brk(0);
+    if increment == 0 {
+        return old_brk;
+    }
+    let new_brk = old_brk + increment;
+    if new_brk < 0 || sys_brk(new_brk as usize) != new_brk {
+        return -1;
+    }
+    old_brk
 }
 
 pub fn sleep(period_ms: usize) {
@@ -0,0 +1,95 @@
+//! 基于内核中的锁、信号量和条件变量的安全封装。内核对象没有销毁的系统调用，它们在进程退出时才被回收，
+//! 所以这些类型被丢弃时什么也不做
+use crate::{
+    condvar_create, condvar_signal, condvar_wait, mutex_blocking_create, mutex_lock, mutex_unlock,
+    semaphore_create, semaphore_down, semaphore_up,
+};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+//保护类型为 T 的数据的阻塞锁，只能通过 lock 返回的 MutexGuard 访问数据，MutexGuard 被丢弃时释放锁
+pub struct Mutex<T> {
+    id: usize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(data: T) -> Self {
+        let id = mutex_blocking_create();
+        assert!(id >= 0);
+        Self {
+            id: id as usize,
+            data: UnsafeCell::new(data),
+        }
+    }
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        //锁一定存在，只有进程收到 SIGKILL 时才会失败，而此时进程不会再回到用户态
+        assert_eq!(mutex_lock(self.id), 0);
+        MutexGuard { mutex: self }
+    }
+}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        mutex_unlock(self.mutex.id);
+    }
+}
+
+//计数信号量
+pub struct Semaphore {
+    id: usize,
+}
+
+impl Semaphore {
+    pub fn new(res_count: usize) -> Self {
+        let id = semaphore_create(res_count);
+        assert!(id >= 0);
+        Self { id: id as usize }
+    }
+    pub fn up(&self) {
+        semaphore_up(self.id);
+    }
+    pub fn down(&self) {
+        assert_eq!(semaphore_down(self.id), 0);
+    }
+}
+
+//条件变量，与 Mutex 配合使用。wait 可能被信号提前唤醒，需要在循环中检查等待的条件
+pub struct Condvar {
+    id: usize,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        let id = condvar_create();
+        assert!(id >= 0);
+        Self { id: id as usize }
+    }
+    pub fn signal(&self) {
+        condvar_signal(self.id);
+    }
+    //释放 guard 持有的锁并等待，返回时重新持有这个锁
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        assert_eq!(condvar_wait(self.id, guard.mutex.id), 0);
+        guard
+    }
+}
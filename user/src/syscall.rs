@@ -1,16 +1,61 @@
+use super::{SignalAction, Stat, SysInfo};
 use core::arch::asm;
 
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_SYMLINKAT: usize = 36;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_CHDIR: usize = 49;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PREAD64: usize = 67;
+const SYSCALL_PWRITE64: usize = 68;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+//sigreturn 的 syscall ID 是 139 ，它只在用户库的 __sigreturn 中通过汇编调用
+const SYSCALL_SETPRIORITY: usize = 140;
+const SYSCALL_GETPRIORITY: usize = 141;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETTID: usize = 178;
+const SYSCALL_SYSINFO: usize = 179;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMCTL: usize = 195;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SHMDT: usize = 197;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_MSYNC: usize = 227;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -26,10 +71,28 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+//参数多于 3 个的系统调用通过 a0~a5 传递全部 6 个参数
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x15") args[5],
+            in("x17") id
+        );
+    }
+    ret
+}
+
 //在读写一个常规文件之前，应用首先需要通过内核提供的 sys_open 系统调用让该文件在进程的文件描述符表中占一项，
 //并得到操作系统的返回值–文件描述符，即文件关联的表项在文件描述表中的索引值
 /// 功能：打开一个常规文件，并返回可以访问它的文件描述符。
-/// 参数：path 描述要打开的文件的文件名（简单起见，文件系统不需要支持目录，所有的文件都放在根目录 / 下），
+/// 参数：path 描述要打开的文件的路径，以 / 开头的为绝对路径，否则相对于当前工作目录，
 /// flags 描述打开文件的标志，具体含义下面给出。
 /// 返回值：如果出现了错误则返回 -1，否则返回打开常规文件的文件描述符。可能的错误原因是：文件不存在。
 /// syscall ID：56
@@ -37,12 +100,158 @@ pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
 }
 
+/// 功能：将当前工作目录的绝对路径写入缓冲区，路径以 \0 结尾。
+/// 返回值：成功返回路径的长度（不含 \0），缓冲区不够大则返回 -1。
+/// syscall ID：17
+pub fn sys_getcwd(buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_GETCWD,
+        [buffer.as_mut_ptr() as usize, buffer.len(), 0],
+    )
+}
+
+/// 功能：将进程中一个已经打开的文件复制一份并分配到一个新的文件描述符中。
+/// 参数：fd 表示进程中一个已经打开的文件的文件描述符。
+/// 返回值：如果出现了错误则返回 -1，否则能够访问已打开文件的新文件描述符。
+/// 可能的错误原因是：传入的 fd 并不对应一个合法的已打开文件。
+/// syscall ID：23
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0])
+}
+
+/// 功能：将 oldfd 对应的已打开文件复制到文件描述符 newfd ，newfd 原来打开的文件会被关闭。
+/// 返回值：成功返回 newfd ，否则返回 -1。可能的错误原因：oldfd 不合法、oldfd 与 newfd 相同。
+/// syscall ID：24
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> isize {
+    syscall(SYSCALL_DUP3, [oldfd, newfd, flags as usize])
+}
+
+/// 功能：创建一个目录。
+/// 参数：dirfd 为相对路径的起点，AT_FDCWD 表示当前工作目录；path 为要创建的目录路径。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：父目录不存在、同名文件已存在。
+/// syscall ID：34
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: u32) -> isize {
+    syscall(
+        SYSCALL_MKDIRAT,
+        [dirfd as usize, path.as_ptr() as usize, mode as usize],
+    )
+}
+
+/// 功能：删除一个文件或空目录，并回收它占据的磁盘空间。
+/// 参数：dirfd 为相对路径的起点；flags 包含 AT_REMOVEDIR 时删除目录，否则删除文件。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：路径不存在、类型与 flags 不符、目录非空。
+/// syscall ID：35
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+/// 功能：创建一个符号链接，它的内容是路径 target ，不要求 target 已经存在。
+/// 参数：newdirfd/linkpath 给出符号链接的位置，target 中的相对路径在使用时从符号链接所在目录开始解析。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：父目录不存在、同名文件已存在。
+/// syscall ID：36
+pub fn sys_symlinkat(target: &str, newdirfd: isize, linkpath: &str) -> isize {
+    syscall(
+        SYSCALL_SYMLINKAT,
+        [
+            target.as_ptr() as usize,
+            newdirfd as usize,
+            linkpath.as_ptr() as usize,
+        ],
+    )
+}
+
+/// 功能：读出符号链接指向的路径，路径不以 \0 结尾，超出缓冲区的部分被截断。
+/// 返回值：成功返回写入缓冲区的字节数，否则返回 -1。可能的错误原因：路径不存在或不是符号链接。
+/// syscall ID：78
+pub fn sys_readlinkat(dirfd: isize, path: &str, buffer: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_READLINKAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            buffer.as_mut_ptr() as usize,
+            buffer.len(),
+            0,
+            0,
+        ],
+    )
+}
+
+/// 功能：获取一个已打开文件的元数据。
+/// 参数：fd 为文件描述符，st 为保存元数据的结构体。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：fd 不对应一个打开的文件。
+/// syscall ID：80
+pub fn sys_fstat(fd: usize, st: &mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, st as *mut _ as usize, 0])
+}
+
+/// 功能：获取路径对应文件的元数据。
+/// 参数：flags 包含 AT_SYMLINK_NOFOLLOW 时，路径最后一级的符号链接不被跟随。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：路径不存在。
+/// syscall ID：79
+pub fn sys_fstatat(dirfd: isize, path: &str, st: &mut Stat, flags: u32) -> isize {
+    syscall6(
+        SYSCALL_FSTATAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            st as *mut _ as usize,
+            flags as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+/// 功能：为一个普通文件建立硬链接，新旧两个名字指向同一个 inode 。
+/// 参数：olddirfd/oldpath 给出已有的文件，newdirfd/newpath 给出新的名字，flags 目前未使用。
+/// 返回值：成功返回 0，否则返回 -1。可能的错误原因：文件不存在或是目录、新的名字已经存在。
+/// syscall ID：37
+pub fn sys_linkat(
+    olddirfd: isize,
+    oldpath: &str,
+    newdirfd: isize,
+    newpath: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_LINKAT,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+/// 功能：切换当前工作目录。
+/// 返回值：成功返回 0，路径不存在或不是目录则返回 -1。
+/// syscall ID：49
+pub fn sys_chdir(path: &str) -> isize {
+    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
 
-/// 功能：当前进程 fork 出来一个子进程。
-/// 返回值：对于子进程返回 0，对于当前进程则返回子进程的 PID 。
+/// 功能：为当前进程打开一个管道。
+/// 参数：pipe 表示应用地址空间中的一个长度为 2 的 usize 数组的起始地址，内核需要按顺序将管道读端
+/// 和写端的文件描述符写入到数组中。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：传入的地址不合法。
+/// syscall ID：59
+pub fn sys_pipe(pipe: &mut [usize]) -> isize {
+    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+}
+
+/// 功能：当前进程 fork 出来一个子进程。只有单线程的进程可以调用。
+/// 返回值：对于子进程返回 0，对于当前进程则返回子进程的 PID ；进程中还有其它线程时返回 -1 。
 pub fn sys_fork() -> isize {
     syscall(SYSCALL_FORK, [0, 0, 0])
 }
@@ -62,26 +271,60 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+/// 功能：移动文件的读写位置。
+/// 参数：whence 为 SEEK_SET/SEEK_CUR/SEEK_END 时，新的位置分别为 offset 、当前位置加 offset
+/// 和文件末尾加 offset 。可以移动到文件末尾之后，之后的写入会用 0 填充中间的空洞。
+/// 返回值：成功返回新的读写位置，否则返回 -1。可能的错误原因：文件不支持随机访问、新的位置为负数。
+/// syscall ID：62
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence])
+}
+
+/// 功能：从文件的 offset 处读取一段内容到缓冲区，不改变文件的读写位置。
+/// 返回值：如果出现了错误则返回 -1，否则返回实际读到的字节数。
+/// syscall ID：67
+pub fn sys_pread64(fd: usize, buffer: &mut [u8], offset: usize) -> isize {
+    syscall6(
+        SYSCALL_PREAD64,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len(), offset, 0, 0],
+    )
+}
+
+/// 功能：将缓冲区中的内容写入文件的 offset 处，不改变文件的读写位置。
+/// 返回值：如果出现了错误则返回 -1，否则返回实际写入的字节数。
+/// syscall ID：68
+pub fn sys_pwrite64(fd: usize, buffer: &[u8], offset: usize) -> isize {
+    syscall6(
+        SYSCALL_PWRITE64,
+        [fd, buffer.as_ptr() as usize, buffer.len(), offset, 0, 0],
+    )
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
     panic!("sys_exit never returns!");
 }
 
 
-/// 功能：将当前进程的地址空间清空并加载一个特定的可执行文件，返回用户态后开始它的执行。
-/// 参数：path给出了要加载的可执行文件的名字；
-/// 返回值：如果出错的话（如找不到名字相符的可执行文件）则返回 -1，否则不应该返回。
-pub fn sys_exec(path: &str) -> isize {
-    syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
+/// 功能：将当前进程的地址空间清空并加载一个特定的可执行文件，返回用户态后开始它的执行。只有单线程的进程可以调用。
+/// 参数：path给出了要加载的可执行文件的名字；args 和 envs 分别是命令行参数和环境变量数组的起始地址，
+/// 数组的每个元素是一个以 \0 结尾的字符串的地址，并以空指针作为数组的结尾。
+/// 返回值：如果出错的话（如找不到名字相符的可执行文件、进程中还有其它线程）则返回 -1，否则不应该返回。
+pub fn sys_exec(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    syscall(
+        SYSCALL_EXEC,
+        [path.as_ptr() as usize, args.as_ptr() as usize, envs.as_ptr() as usize],
+    )
 }
 
 /// 功能：当前进程等待一个子进程变为僵尸进程，回收其全部资源并收集其返回值。
 /// 参数：pid 表示要等待的子进程的进程 ID，如果为 -1 的话表示等待任意一个子进程；
-/// exit_code 表示保存子进程返回值的地址，如果这个地址为 0 的话表示不必保存。
-/// 返回值：如果要等待的子进程不存在则返回 -1；否则如果要等待的子进程均未结束则返回 -2；
-/// 否则返回结束的子进程的进程 ID。
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+/// exit_code 表示保存子进程返回值的地址，如果这个地址为 0 的话表示不必保存；
+/// options 为 0 时若要等待的子进程均未结束则阻塞，为 WNOHANG 时则立即返回 0 。
+/// 返回值：如果要等待的子进程不存在或者等待时收到了需要处理的信号则返回 -1；否则如果设置了 WNOHANG
+/// 且要等待的子进程均未结束则返回 0；否则返回结束的子进程的进程 ID。
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
 }
 
 pub fn sys_yield() -> isize {
@@ -94,4 +337,227 @@ pub fn sys_get_time() -> isize {
 
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0])
-}
\ No newline at end of file
+}
+
+/// 功能：获取当前线程在进程中的编号，主线程的编号为 0 。
+/// syscall ID：178
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+/// 功能：在当前进程中创建一个新线程，它从 entry 开始执行，arg 作为第一个参数传入，并继承当前线程的信号屏蔽字。
+/// 线程函数不能返回，必须调用 exit 退出；主线程退出时整个进程随之退出。
+/// 返回值：成功时返回新线程的编号，否则返回 -1 。可能的错误原因：新线程的用户栈所在的位置已经被映射。
+/// syscall ID：1000
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
+}
+
+/// 功能：等待当前进程中编号为 tid 的线程退出并回收它。
+/// 参数：exit_code 表示保存线程退出码的地址，如果这个地址为 0 的话表示不必保存。
+/// 返回值：成功时返回 tid ，否则返回 -1 。可能的错误原因：线程不存在、等待的是自己、等待时收到了需要处理的信号。
+/// syscall ID：1002
+pub fn sys_waittid(tid: usize, exit_code: *mut i32) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, exit_code as usize, 0])
+}
+
+/// 功能：创建一个互斥锁。blocking 为真时锁被占用的线程阻塞等待，否则不断让出处理器之后重试。
+/// 锁、信号量和条件变量属于进程，fork 出的子进程与父进程共享它们，exec 之后它们不再可用。
+/// 返回值：锁的编号。
+/// syscall ID：1010
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [blocking as usize, 0, 0])
+}
+
+/// 功能：获取编号为 mutex_id 的锁，锁被占用时等待。等待期间收到的信号在得到锁之后才会被处理，
+/// 但 SIGKILL 会让进程立即退出。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：锁不存在。
+/// syscall ID：1011
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [mutex_id, 0, 0])
+}
+
+/// 功能：释放编号为 mutex_id 的锁。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：锁不存在、锁没有被持有。
+/// syscall ID：1012
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [mutex_id, 0, 0])
+}
+
+/// 功能：创建一个初始计数为 res_count 的信号量。
+/// 返回值：信号量的编号。
+/// syscall ID：1020
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [res_count, 0, 0])
+}
+
+/// 功能：将信号量的计数加一，有线程在等待时唤醒其中一个。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：信号量不存在。
+/// syscall ID：1021
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [sem_id, 0, 0])
+}
+
+/// 功能：将信号量的计数减一，计数为 0 时等待，信号的处理方式与 mutex_lock 相同。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：信号量不存在。
+/// syscall ID：1022
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
+}
+
+/// 功能：创建一个条件变量。
+/// 返回值：条件变量的编号。
+/// syscall ID：1030
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0])
+}
+
+/// 功能：唤醒一个在条件变量上等待的线程。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：条件变量不存在。
+/// syscall ID：1031
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [condvar_id, 0, 0])
+}
+
+/// 功能：释放编号为 mutex_id 的锁并在条件变量上等待，被唤醒之后重新获取这个锁再返回。
+/// 线程也可能被信号唤醒，所以需要在循环中检查等待的条件。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：条件变量或者锁不存在。
+/// syscall ID：1032
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0])
+}
+
+/// 功能：在用户变量 uaddr 上等待或者唤醒等待者，futex 按照变量所在的物理地址区分，
+/// 所以同一进程的线程之间、以及通过共享内存映射同一个变量的进程之间都可以使用。
+/// futex_op 为 FUTEX_WAIT(0) 时，如果 uaddr 处的值仍然等于 val 就阻塞，直到被 FUTEX_WAKE 唤醒；
+/// futex_op 为 FUTEX_WAKE(1) 时唤醒最多 val 个在 uaddr 上等待的线程。
+/// 返回值：FUTEX_WAIT 被唤醒时返回 0 ，FUTEX_WAKE 返回唤醒的线程数量，出错返回 -1 。可能的错误原因：
+/// uaddr 没有按 4 字节对齐或者不可写、FUTEX_WAIT 时 uaddr 处的值不等于 val 、有需要处理的信号、futex_op 不合法。
+/// syscall ID：98
+pub fn sys_futex(uaddr: *const u32, futex_op: usize, val: usize) -> isize {
+    syscall(SYSCALL_FUTEX, [uaddr as usize, futex_op, val])
+}
+
+/// 功能：向进程 ID 为 pid 的进程发送编号为 signum 的信号，signum 为 0 时只检查进程是否存在。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：进程不存在或者已经退出、signum 不合法。
+/// syscall ID：129
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    syscall(SYSCALL_KILL, [pid, signum, 0])
+}
+
+/// 功能：设置信号 signum 的处理方式。
+/// 参数：act 不为空指针时设置新的处理方式；oldact 不为空指针时写入原来的处理方式。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：signum 不合法、试图修改 SIGKILL 或 SIGSTOP 的处理方式。
+/// syscall ID：134
+pub fn sys_sigaction(signum: usize, act: *const SignalAction, oldact: *mut SignalAction) -> isize {
+    syscall(SYSCALL_SIGACTION, [signum, act as usize, oldact as usize])
+}
+
+/// 功能：按照 how 修改信号屏蔽字，被屏蔽的信号会等到解除屏蔽之后再处理。
+/// 参数：how 为 SIG_BLOCK 、SIG_UNBLOCK 或 SIG_SETMASK ；set 不为空指针时修改屏蔽字；
+/// oldset 不为空指针时写入原来的屏蔽字。SIGKILL 和 SIGSTOP 不能被屏蔽。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：how 不合法。
+/// syscall ID：135
+pub fn sys_sigprocmask(how: usize, set: *const u32, oldset: *mut u32) -> isize {
+    syscall(SYSCALL_SIGPROCMASK, [how, set as usize, oldset as usize])
+}
+
+/// 功能：设置进程中所有线程的 nice 值，nice 值越小优先级越高，超出 [-20, 19] 的值被截断。
+/// 只有使用步长调度时 nice 值才会影响调度，之后创建的线程和 fork 出的子进程继承这个值。
+/// 参数：which 只能为 PRIO_PROCESS ；who 为进程 ID ，为 0 时表示当前进程。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：which 不合法、进程不存在或者已经退出。
+/// syscall ID：140
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    syscall(SYSCALL_SETPRIORITY, [which, who, nice as usize])
+}
+
+/// 功能：读取进程主线程的 nice 值，参数与 setpriority 相同。
+/// 返回值：成功返回 20 - nice ，总是正数，否则返回 -1 。可能的错误原因：which 不合法、进程不存在或者已经退出。
+/// syscall ID：141
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    syscall(SYSCALL_GETPRIORITY, [which, who, 0])
+}
+
+/// 功能：获取物理页帧和内核堆的使用情况，写入 info 指向的 SysInfo 结构体。
+/// 返回值：成功返回 0 ，否则返回 -1 。
+/// syscall ID：179
+pub fn sys_sysinfo(info: &mut SysInfo) -> isize {
+    syscall(SYSCALL_SYSINFO, [info as *mut _ as usize, 0, 0])
+}
+
+/// 功能：获取 key 对应的共享内存段。key 为 IPC_PRIVATE 时总是新建一个；否则 flags 包含 IPC_CREAT 时
+/// 在不存在时新建，同时包含 IPC_EXCL 时要求之前不存在。新建的共享内存段大小为 size 向上取整到页，内容全为 0 。
+/// 返回值：成功时返回共享内存段的编号，否则返回 -1 。
+/// syscall ID：194
+pub fn sys_shmget(key: usize, size: usize, flags: usize) -> isize {
+    syscall(SYSCALL_SHMGET, [key, size, flags])
+}
+
+/// 功能：控制共享内存段，目前只支持 IPC_RMID ：删除共享内存段，已经建立的映射仍然有效。
+/// 返回值：成功返回 0 ，否则返回 -1 。
+/// syscall ID：195
+pub fn sys_shmctl(shmid: usize, cmd: usize) -> isize {
+    syscall(SYSCALL_SHMCTL, [shmid, cmd, 0])
+}
+
+/// 功能：将共享内存段映射到 addr 处，addr 为 0 时由内核选择位置；flags 包含 SHM_RDONLY 时只读映射。
+/// fork 出的子进程继承父进程的映射。
+/// 返回值：成功时返回映射的起始地址，否则返回 -1 。
+/// syscall ID：196
+pub fn sys_shmat(shmid: usize, addr: usize, flags: usize) -> isize {
+    syscall(SYSCALL_SHMAT, [shmid, addr, flags])
+}
+
+/// 功能：解除 shmat 在 addr 处建立的映射。
+/// 返回值：成功返回 0 ，否则返回 -1 。
+/// syscall ID：197
+pub fn sys_shmdt(addr: usize) -> isize {
+    syscall(SYSCALL_SHMDT, [addr, 0, 0])
+}
+
+/// 功能：在地址空间中映射一段匿名内存或者一个普通文件中从 offset 开始的内容。
+/// 参数：start 为 0 时由内核选择映射的位置，否则必须按页对齐且不能与已有的映射重叠；len 会被向上取整到页的大小；
+/// prot 是 PROT_READ/PROT_WRITE/PROT_EXEC 的组合，不能为 0；flags 为 MAP_PRIVATE | MAP_ANONYMOUS 时映射匿名内存，
+/// fd 和 offset 被忽略；为 MAP_SHARED 或 MAP_PRIVATE 时映射文件 fd ，offset 必须按页对齐，
+/// 共享映射中的修改会写回文件，私有映射中的修改只有自己可见。
+/// 返回值：成功时返回映射的起始地址，否则返回 -1 。
+/// syscall ID：222
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    syscall6(SYSCALL_MMAP, [start, len, prot, flags, fd, offset])
+}
+
+/// 功能：将 [start, start + len) 中页面的访问权限修改为 prot 。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：start 没有按页对齐、区间中有没有被映射的页面、prot 不合法、
+/// 试图让只读打开的文件的共享映射变为可写。
+/// syscall ID：226
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [start, len, prot])
+}
+
+/// 功能：将 [start, start + len) 中共享文件映射被修改过的页面写回文件。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：start 没有按页对齐。
+/// syscall ID：227
+pub fn sys_msync(start: usize, len: usize, flags: usize) -> isize {
+    syscall(SYSCALL_MSYNC, [start, len, flags])
+}
+
+/// 功能：解除 [start, start + len) 中页面的映射，可以只解除某次 mmap 得到的区域的一部分。
+/// 返回值：成功返回 0 ，否则返回 -1 。可能的错误原因：start 没有按页对齐、len 为 0 。
+/// syscall ID：215
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [start, len, 0])
+}
+
+/// 功能：将程序断点（堆的末尾）设置为 brk ，堆从最后一个 ELF 段之后的页边界开始。
+/// 返回值：返回设置之后的程序断点。失败时程序断点不变，所以 brk 为 0 时返回当前的程序断点。
+/// syscall ID：214
+pub fn sys_brk(brk: usize) -> isize {
+    syscall(SYSCALL_BRK, [brk, 0, 0])
+}